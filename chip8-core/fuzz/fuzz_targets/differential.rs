@@ -0,0 +1,12 @@
+#![no_main]
+
+use chip8_core_fuzz::{FuzzInput, run};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(input) = FuzzInput::parse(data)
+        && let Err(divergence) = run(&input)
+    {
+        panic!("{divergence}");
+    }
+});
@@ -0,0 +1,170 @@
+//! Runs ```Chip8``` next to the reference interpreter in ```reference``` and
+//! reports the first instruction after which they disagree.
+
+pub mod reference;
+
+use chip8_core::{
+    emulator::{Chip8, INSTRUCTIONS_PER_FRAME},
+    error::{Chip8Error, ErrorPolicy},
+    quirks::Quirks,
+};
+
+use reference::{Fault, Machine};
+
+/// A fuzz case, read from raw bytes as:
+///
+/// - 1 byte of quirk flags, one bit per ```Quirks``` field in declaration order
+/// - 1 byte frame count, then that many little endian 16 bit key masks, one per frame
+/// - the rest is the rom
+#[derive(Debug, Clone)]
+pub struct FuzzInput<'a> {
+    pub quirks: Quirks,
+    pub frames: Vec<u16>,
+    pub rom: &'a [u8],
+}
+
+impl<'a> FuzzInput<'a> {
+    /// ```None``` when there aren't enough bytes for the header and keys.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        let (&[flags, frame_count], rest) = data.split_first_chunk::<2>()?;
+        let flag = |bit: u8| flags >> bit & 1 == 1;
+        let quirks = Quirks {
+            shift_uses_vy: flag(0),
+            jump_uses_vx: flag(1),
+            load_store_increments_i: flag(2),
+            vf_reset: flag(3),
+            clip_sprites: flag(4),
+            display_wait: flag(5),
+            stack_depth: if flag(6) { 16 } else { 12 },
+            memory_mapped_stack: flag(7),
+        };
+
+        let key_bytes = frame_count as usize * 2;
+        if rest.len() < key_bytes {
+            return None;
+        }
+        let (keys, rom) = rest.split_at(key_bytes);
+        let frames = keys
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        Some(Self {
+            quirks,
+            frames,
+            rom,
+        })
+    }
+}
+
+/// Runs both interpreters a frame at a time, comparing them after every
+/// instruction. Stops early once both have failed in the same way.
+pub fn run(input: &FuzzInput) -> Result<(), String> {
+    let mut em = Chip8::new();
+    em.quirks = input.quirks;
+    em.error_policy = ErrorPolicy::Halt;
+    if em.load_rom_bytes(input.rom).is_err() {
+        return Ok(());
+    }
+    let mut reference = Machine::new(input.quirks, em.memory());
+
+    for (frame, keys) in input.frames.iter().enumerate() {
+        em.set_keys(*keys);
+        em.decrement_timers();
+        reference.tick(*keys);
+
+        for instruction in 0..INSTRUCTIONS_PER_FRAME {
+            let pc = reference.pc;
+            let opcode = reference.next_opcode();
+            let at = format!("frame {frame}, instruction {instruction}, {opcode:04X} at {pc:#05X}");
+
+            let error = em.step().err().map(fault);
+            // CXNN can't be predicted, so the reference draws the same number
+            let random = if opcode >> 12 == 0xC {
+                em.v()[(opcode >> 8 & 0xF) as usize]
+            } else {
+                0
+            };
+            let expected = reference.step(random).err();
+
+            if error != expected {
+                return Err(format!(
+                    "{at}: failed with {error:?}, reference with {expected:?}"
+                ));
+            }
+            if error.is_some() {
+                return Ok(());
+            }
+            compare(&em, &reference).map_err(|e| format!("{at}: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+fn fault(error: Chip8Error) -> Fault {
+    match error {
+        Chip8Error::StackUnderflow { pc } => Fault::StackUnderflow { pc },
+        Chip8Error::StackOverflow { pc } => Fault::StackOverflow { pc },
+        Chip8Error::PcOutOfBounds { pc } => Fault::PcOutOfBounds { pc },
+        Chip8Error::MemoryOutOfBounds { pc, addr } => Fault::MemoryOutOfBounds { pc, addr },
+        Chip8Error::UnknownOpcode { pc, opcode } => Fault::UnknownOpcode { pc, opcode },
+        Chip8Error::Io(_) | Chip8Error::RomTooLarge { .. } => {
+            unreachable!("{error} while running")
+        }
+    }
+}
+
+fn compare(em: &Chip8, reference: &Machine) -> Result<(), String> {
+    fn check<T: PartialEq + std::fmt::Debug>(
+        what: &str,
+        actual: T,
+        expected: T,
+    ) -> Result<(), String> {
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(format!(
+                "{what} is {actual:X?}, reference has {expected:X?}"
+            ))
+        }
+    }
+
+    check("PC", em.pc(), reference.pc)?;
+    check("I", em.i(), reference.i)?;
+    check("V", em.v(), &reference.v)?;
+    check("stack", em.stack(), &reference.stack)?;
+    check("delay timer", em.delay_timer(), reference.delay_timer)?;
+    check("sound timer", em.sound_timer(), reference.sound_timer)?;
+    check("exited", em.has_exited(), reference.exited)?;
+    check(
+        "resolution",
+        (em.width(), em.height()),
+        (reference.width(), reference.height()),
+    )?;
+    if em.memory() != reference.memory {
+        let addr = (0..reference.memory.len())
+            .find(|&a| em.memory()[a] != reference.memory[a])
+            .unwrap();
+        check(
+            &format!("memory at {addr:#06X}"),
+            em.memory()[addr],
+            reference.memory[addr],
+        )?;
+    }
+    let expected: Vec<u32> = reference
+        .planes
+        .iter()
+        .map(|planes| em.palette[*planes as usize])
+        .collect();
+    if em.framebuffer() != expected {
+        let pixel = (0..expected.len())
+            .find(|&p| em.framebuffer()[p] != expected[p])
+            .unwrap();
+        let width = reference.width();
+        check(
+            &format!("pixel {},{}", pixel % width, pixel / width),
+            em.framebuffer()[pixel],
+            expected[pixel],
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,397 @@
+//! A second Chip-8 interpreter, written to be obviously right rather than
+//! fast or tidy. It shares no code with ```Chip8``` beyond the quirk settings,
+//! so the two only agree when they both follow the spec.
+
+use chip8_core::quirks::Quirks;
+
+pub const MEMORY_SIZE: usize = 0x10000;
+const STACK_ADDR: usize = 0xEA0;
+
+/// Why an instruction couldn't run. Mirrors ```Chip8Error```.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    StackUnderflow { pc: usize },
+    StackOverflow { pc: usize },
+    PcOutOfBounds { pc: usize },
+    MemoryOutOfBounds { pc: usize, addr: usize },
+    UnknownOpcode { pc: usize, opcode: u16 },
+}
+
+pub struct Machine {
+    pub memory: Vec<u8>,
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: usize,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub hires: bool,
+    /// One byte per pixel, bit 0 for plane 1 and bit 1 for plane 2.
+    pub planes: Vec<u8>,
+    pub selected_planes: u8,
+    pub rpl: [u8; 16],
+    pub exited: bool,
+    pub halted: bool,
+    quirks: Quirks,
+    frame_keys: u16,
+    previous_frame_keys: u16,
+    key_wait: Option<u8>,
+    vblank: bool,
+}
+
+impl Machine {
+    /// Starts from ```memory``` as loaded by the interpreter under test,
+    /// so the font and rom are wherever it put them.
+    pub fn new(quirks: Quirks, memory: &[u8]) -> Self {
+        Self {
+            memory: memory.to_vec(),
+            v: [0; 16],
+            i: 0,
+            pc: 0x200,
+            stack: Vec::new(),
+            delay_timer: 0,
+            sound_timer: 0,
+            hires: false,
+            planes: vec![0; 64 * 32],
+            selected_planes: 1,
+            rpl: [0; 16],
+            exited: false,
+            halted: false,
+            quirks,
+            frame_keys: 0,
+            previous_frame_keys: 0,
+            key_wait: None,
+            vblank: false,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        if self.hires { 128 } else { 64 }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.hires { 64 } else { 32 }
+    }
+
+    /// The 60Hz tick, which also reads the keys for the coming frame.
+    pub fn tick(&mut self, keys: u16) {
+        self.vblank = true;
+        self.previous_frame_keys = self.frame_keys;
+        self.frame_keys = keys;
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    /// The opcode about to run, or 0 past the end of memory.
+    pub fn next_opcode(&self) -> u16 {
+        let byte = |addr: usize| self.memory.get(addr).copied().unwrap_or(0) as u16;
+        byte(self.pc) << 8 | byte(self.pc + 1)
+    }
+
+    /// Runs one instruction. CXNN uses ```random``` as its random number.
+    pub fn step(&mut self, random: u8) -> Result<(), Fault> {
+        if self.exited || self.halted {
+            return Ok(());
+        }
+        let result = self.run(random);
+        if result.is_err() {
+            self.halted = true;
+        }
+        result
+    }
+
+    fn run(&mut self, random: u8) -> Result<(), Fault> {
+        let pc = self.pc;
+        if pc + 1 >= MEMORY_SIZE {
+            self.pc = (pc + 2) % MEMORY_SIZE;
+            return Err(Fault::PcOutOfBounds { pc });
+        }
+        let opcode = self.next_opcode();
+        self.pc += 2;
+
+        let x = (opcode >> 8 & 0xF) as usize;
+        let y = (opcode >> 4 & 0xF) as usize;
+        let n = (opcode & 0xF) as usize;
+        let nn = (opcode & 0xFF) as u8;
+        let nnn = opcode & 0xFFF;
+        let vx = self.v[x];
+        let vy = self.v[y];
+
+        match (opcode >> 12, x, y, n) {
+            (0x0, 0, 0xE, 0x0) => {
+                for pixel in self.planes.iter_mut() {
+                    *pixel &= !self.selected_planes;
+                }
+            }
+            (0x0, 0, 0xE, 0xE) => {
+                let Some(mut addr) = self.stack.pop() else {
+                    return Err(Fault::StackUnderflow { pc });
+                };
+                if self.quirks.memory_mapped_stack {
+                    let slot = STACK_ADDR + self.stack.len() * 2;
+                    addr = (self.memory[slot] as u16) << 8 | self.memory[slot + 1] as u16;
+                }
+                self.pc = addr as usize;
+            }
+            (0x0, 0, 0xC, _) => self.scroll(0, n as isize),
+            (0x0, 0, 0xD, _) => self.scroll(0, -(n as isize)),
+            (0x0, 0, 0xF, 0xB) => self.scroll(4, 0),
+            (0x0, 0, 0xF, 0xC) => self.scroll(-4, 0),
+            (0x0, 0, 0xF, 0xD) => self.exited = true,
+            (0x0, 0, 0xF, 0xE) => {
+                self.hires = false;
+                self.planes = vec![0; 64 * 32];
+            }
+            (0x0, 0, 0xF, 0xF) => {
+                self.hires = true;
+                self.planes = vec![0; 128 * 64];
+            }
+            // machine code routines are ignored
+            (0x0, _, _, _) => {}
+            (0x1, _, _, _) => self.pc = nnn as usize,
+            (0x2, _, _, _) => {
+                if self.stack.len() >= self.quirks.stack_depth {
+                    return Err(Fault::StackOverflow { pc });
+                }
+                if self.quirks.memory_mapped_stack {
+                    let slot = STACK_ADDR + self.stack.len() * 2;
+                    self.memory[slot] = (self.pc >> 8) as u8;
+                    self.memory[slot + 1] = self.pc as u8;
+                }
+                self.stack.push(self.pc as u16);
+                self.pc = nnn as usize;
+            }
+            (0x3, _, _, _) => self.skip_if(vx == nn),
+            (0x4, _, _, _) => self.skip_if(vx != nn),
+            (0x5, _, _, 0x0) => self.skip_if(vx == vy),
+            (0x5, _, _, 0x2) => {
+                let registers = register_range(x, y);
+                self.check_memory(pc, registers.len())?;
+                for (offset, register) in registers.into_iter().enumerate() {
+                    self.memory[self.i as usize + offset] = self.v[register];
+                }
+            }
+            (0x5, _, _, 0x3) => {
+                let registers = register_range(x, y);
+                self.check_memory(pc, registers.len())?;
+                for (offset, register) in registers.into_iter().enumerate() {
+                    self.v[register] = self.memory[self.i as usize + offset];
+                }
+            }
+            (0x6, _, _, _) => self.v[x] = nn,
+            (0x7, _, _, _) => self.v[x] = vx.wrapping_add(nn),
+            (0x8, _, _, 0x0) => self.v[x] = vy,
+            (0x8, _, _, 0x1) => self.logic(x, vx | vy),
+            (0x8, _, _, 0x2) => self.logic(x, vx & vy),
+            (0x8, _, _, 0x3) => self.logic(x, vx ^ vy),
+            (0x8, _, _, 0x4) => {
+                let sum = vx as u16 + vy as u16;
+                self.set_with_flag(x, sum as u8, (sum > 0xFF) as u8);
+            }
+            (0x8, _, _, 0x5) => self.set_with_flag(x, vx.wrapping_sub(vy), (vx >= vy) as u8),
+            (0x8, _, _, 0x7) => self.set_with_flag(x, vy.wrapping_sub(vx), (vy >= vx) as u8),
+            (0x8, _, _, 0x6) => {
+                let value = if self.quirks.shift_uses_vy { vy } else { vx };
+                self.set_with_flag(x, value >> 1, value & 1);
+            }
+            (0x8, _, _, 0xE) => {
+                let value = if self.quirks.shift_uses_vy { vy } else { vx };
+                self.set_with_flag(x, value << 1, value >> 7);
+            }
+            (0x9, _, _, 0x0) => self.skip_if(vx != vy),
+            (0xA, _, _, _) => self.i = nnn,
+            (0xB, _, _, _) => {
+                let offset = if self.quirks.jump_uses_vx {
+                    vx
+                } else {
+                    self.v[0]
+                };
+                self.pc = nnn as usize + offset as usize;
+            }
+            (0xC, _, _, _) => self.v[x] = random & nn,
+            (0xD, _, _, _) => return self.draw(pc, vx, vy, n),
+            (0xE, _, 0x9, 0xE) => self.skip_if(self.key_down(vx)),
+            (0xE, _, 0xA, 0x1) => self.skip_if(!self.key_down(vx)),
+            (0xF, 0, 0x0, 0x0) => {
+                if self.pc + 1 >= MEMORY_SIZE {
+                    return Err(Fault::PcOutOfBounds { pc: self.pc });
+                }
+                self.i = self.next_opcode();
+                self.pc += 2;
+            }
+            (0xF, _, 0x0, 0x1) => self.selected_planes = x as u8 & 0b11,
+            // the pattern itself isn't compared, only that it's readable
+            (0xF, 0, 0x0, 0x2) => self.check_memory(pc, 16)?,
+            (0xF, _, 0x0, 0x7) => self.v[x] = self.delay_timer,
+            (0xF, _, 0x0, 0xA) => self.wait_for_key(x),
+            (0xF, _, 0x1, 0x5) => self.delay_timer = vx,
+            (0xF, _, 0x1, 0x8) => self.sound_timer = vx,
+            (0xF, _, 0x1, 0xE) => self.i = self.i.wrapping_add(vx as u16),
+            (0xF, _, 0x2, 0x9) => self.i = 0x50 + 5 * (vx & 0xF) as u16,
+            (0xF, _, 0x3, 0x0) => self.i = 0xA0 + 10 * (vx & 0xF) as u16,
+            (0xF, _, 0x3, 0x3) => {
+                self.check_memory(pc, 3)?;
+                let i = self.i as usize;
+                self.memory[i] = vx / 100;
+                self.memory[i + 1] = vx / 10 % 10;
+                self.memory[i + 2] = vx % 10;
+            }
+            (0xF, _, 0x3, 0xA) => {}
+            (0xF, _, 0x5, 0x5) => {
+                self.check_memory(pc, x + 1)?;
+                for register in 0..=x {
+                    self.memory[self.i as usize + register] = self.v[register];
+                }
+                self.after_load_store(x);
+            }
+            (0xF, _, 0x6, 0x5) => {
+                self.check_memory(pc, x + 1)?;
+                for register in 0..=x {
+                    self.v[register] = self.memory[self.i as usize + register];
+                }
+                self.after_load_store(x);
+            }
+            (0xF, _, 0x7, 0x5) => {
+                for register in 0..=x {
+                    self.rpl[register] = self.v[register];
+                }
+            }
+            (0xF, _, 0x8, 0x5) => {
+                for register in 0..=x {
+                    self.v[register] = self.rpl[register];
+                }
+            }
+            _ => return Err(Fault::UnknownOpcode { pc, opcode }),
+        }
+        Ok(())
+    }
+
+    fn skip_if(&mut self, condition: bool) {
+        if condition {
+            // F000 NNNN is skipped whole
+            self.pc += if self.next_opcode() == 0xF000 { 4 } else { 2 };
+        }
+    }
+
+    fn logic(&mut self, x: usize, result: u8) {
+        self.v[x] = result;
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
+    }
+
+    /// The flag is written last, so it wins when ```x``` is F.
+    fn set_with_flag(&mut self, x: usize, result: u8, flag: u8) {
+        self.v[x] = result;
+        self.v[0xF] = flag;
+    }
+
+    fn after_load_store(&mut self, x: usize) {
+        if self.quirks.load_store_increments_i {
+            self.i = self.i.wrapping_add(x as u16 + 1);
+        }
+    }
+
+    fn check_memory(&self, pc: usize, len: usize) -> Result<(), Fault> {
+        let addr = self.i as usize;
+        if addr + len > MEMORY_SIZE {
+            return Err(Fault::MemoryOutOfBounds { pc, addr });
+        }
+        Ok(())
+    }
+
+    fn key_down(&self, key: u8) -> bool {
+        self.frame_keys >> (key & 0xF) & 1 == 1
+    }
+
+    fn wait_for_key(&mut self, x: usize) {
+        match self.key_wait {
+            Some(key) if !self.key_down(key) => {
+                self.v[x] = key;
+                self.key_wait = None;
+            }
+            Some(_) => self.pc -= 2,
+            None => {
+                let pressed = self.frame_keys & !self.previous_frame_keys;
+                if pressed != 0 {
+                    self.key_wait = Some(pressed.trailing_zeros() as u8);
+                }
+                self.pc -= 2;
+            }
+        }
+    }
+
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        let old = self.planes.clone();
+        for y in 0..height {
+            for x in 0..width {
+                let (from_x, from_y) = (x - dx, y - dy);
+                let moved = if from_x >= 0 && from_x < width && from_y >= 0 && from_y < height {
+                    old[(from_y * width + from_x) as usize] & self.selected_planes
+                } else {
+                    0
+                };
+                let pixel = (y * width + x) as usize;
+                self.planes[pixel] = old[pixel] & !self.selected_planes | moved;
+            }
+        }
+    }
+
+    fn draw(&mut self, pc: usize, vx: u8, vy: u8, n: usize) -> Result<(), Fault> {
+        if self.quirks.display_wait {
+            if !self.vblank {
+                self.pc -= 2;
+                return Ok(());
+            }
+            self.vblank = false;
+        }
+
+        let (width, height) = (self.width(), self.height());
+        let (left, top) = (vx as usize % width, vy as usize % height);
+        self.v[0xF] = 0;
+        let (rows, columns) = if n == 0 { (16, 16) } else { (n, 8) };
+        let sprite_bytes = rows * columns / 8;
+        let plane_count = (self.selected_planes & 1) + (self.selected_planes >> 1 & 1);
+        self.check_memory(pc, sprite_bytes * plane_count as usize)?;
+
+        let mut addr = self.i as usize;
+        for plane in [1, 2] {
+            if self.selected_planes & plane == 0 {
+                continue;
+            }
+            for row in 0..rows {
+                for column in 0..columns {
+                    let byte = self.memory[addr + row * columns / 8 + column / 8];
+                    if byte >> (7 - column % 8) & 1 == 0 {
+                        continue;
+                    }
+                    let (mut x, mut y) = (left + column, top + row);
+                    if x >= width || y >= height {
+                        if self.quirks.clip_sprites {
+                            continue;
+                        }
+                        x %= width;
+                        y %= height;
+                    }
+                    let pixel = y * width + x;
+                    if self.planes[pixel] & plane != 0 {
+                        self.v[0xF] = 1;
+                    }
+                    self.planes[pixel] ^= plane;
+                }
+            }
+            addr += sprite_bytes;
+        }
+        Ok(())
+    }
+}
+
+/// V```x``` to V```y``` in the order they're stored, which is backwards when ```x > y```.
+fn register_range(x: usize, y: usize) -> Vec<usize> {
+    if x <= y {
+        (x..=y).collect()
+    } else {
+        (y..=x).rev().collect()
+    }
+}
@@ -0,0 +1,32 @@
+//! Replays the cases in ```regressions/```, each an input the fuzzer once
+//! found the interpreter and the reference disagreeing on or panicking with.
+
+use std::{fs, path::PathBuf};
+
+use chip8_core_fuzz::{FuzzInput, run};
+
+#[test]
+fn regressions() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("regressions");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no cases in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let data = fs::read(&path).unwrap();
+        let name = path.file_name().unwrap().to_string_lossy();
+        match FuzzInput::parse(&data) {
+            Some(input) => {
+                if let Err(divergence) = run(&input) {
+                    failures.push(format!("{name}: {divergence}"));
+                }
+            }
+            None => failures.push(format!("{name}: too short to be a case")),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
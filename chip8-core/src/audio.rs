@@ -0,0 +1,79 @@
+use std::{cell::RefCell, rc::Rc};
+
+pub const DEFAULT_PITCH: u8 = 64;
+/// Played until a program loads its own pattern with F002.
+/// A 500Hz square wave at the default pitch.
+pub const DEFAULT_PATTERN: [u8; 16] = [0xF0; 16];
+
+/// Where the emulator sends its sound. The tone plays for exactly as long
+/// as the sound timer is above 0.
+pub trait AudioSink {
+    /// The sound timer went from 0 to a positive value.
+    fn start_tone(&mut self);
+    /// The sound timer reached 0.
+    fn stop_tone(&mut self);
+    /// XO-CHIP 128 bit pattern played while the tone is on, loaded with F002.
+    fn set_pattern(&mut self, pattern: [u8; 16]);
+    /// XO-CHIP pattern playback rate, set with FX3A.
+    fn set_pitch(&mut self, pitch: u8);
+}
+
+/// Throws all sound away.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullSink;
+
+impl AudioSink for NullSink {
+    fn start_tone(&mut self) {}
+    fn stop_tone(&mut self) {}
+    fn set_pattern(&mut self, _pattern: [u8; 16]) {}
+    fn set_pitch(&mut self, _pitch: u8) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioEvent {
+    StartTone,
+    StopTone,
+    Pattern([u8; 16]),
+    Pitch(u8),
+}
+
+/// Remembers everything sent to it, for checking what a rom played.
+/// Clones share the same events, so keep one to read them after
+/// handing the other to the emulator.
+#[derive(Debug, Default, Clone)]
+pub struct RecordingSink {
+    events: Rc<RefCell<Vec<AudioEvent>>>,
+}
+
+impl RecordingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything recorded so far, oldest first.
+    pub fn events(&self) -> Vec<AudioEvent> {
+        self.events.borrow().clone()
+    }
+
+    pub fn clear(&self) {
+        self.events.borrow_mut().clear();
+    }
+}
+
+impl AudioSink for RecordingSink {
+    fn start_tone(&mut self) {
+        self.events.borrow_mut().push(AudioEvent::StartTone);
+    }
+
+    fn stop_tone(&mut self) {
+        self.events.borrow_mut().push(AudioEvent::StopTone);
+    }
+
+    fn set_pattern(&mut self, pattern: [u8; 16]) {
+        self.events.borrow_mut().push(AudioEvent::Pattern(pattern));
+    }
+
+    fn set_pitch(&mut self, pitch: u8) {
+        self.events.borrow_mut().push(AudioEvent::Pitch(pitch));
+    }
+}
@@ -0,0 +1,1131 @@
+use sha1::{Digest, Sha1};
+use std::{fs, path::Path};
+
+use crate::{
+    audio::{AudioSink, DEFAULT_PATTERN, DEFAULT_PITCH, NullSink},
+    error::{Chip8Error, ErrorPolicy},
+    instruction::Instruction,
+    quirks::Quirks,
+    savestate::{Snapshot, SnapshotError},
+    timing::cosmac_vip_cycles,
+};
+
+#[cfg(test)]
+mod builder;
+#[cfg(test)]
+mod tests;
+
+const FONT_STARTING_ADDR: usize = 0x50;
+const BIG_FONT_STARTING_ADDR: usize = 0xA0;
+// XO-CHIP extends the original 4K address space to 64K
+const MEMORY_SIZE: usize = 0x10000;
+const PROGRAM_STARTING_ADDR: usize = 0x200;
+// where the COSMAC VIP interpreter kept its call stack, see Quirks::memory_mapped_stack
+const MEMORY_STACK_ADDR: usize = 0xEA0;
+/// Calls that fit in the VIP's stack area, 0xEA0 to 0xECF.
+pub const MAX_MEMORY_MAPPED_STACK_DEPTH: usize = 24;
+pub const LORES_WIDTH: usize = 64;
+pub const LORES_HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+/// Instructions run between each 60Hz timer tick.
+pub const INSTRUCTIONS_PER_FRAME: usize = 12;
+
+pub struct Chip8 {
+    memory: [u8; MEMORY_SIZE],
+    // XO-CHIP bitplanes, one byte per pixel. bit 0 is plane 1 & bit 1 is plane 2.
+    // sized for the current resolution, see width() & height()
+    planes: Vec<u8>,
+    // planes composited into colors from the palette
+    display: Vec<u32>,
+    hires: bool,
+    selected_planes: u8,
+    /// Colors for each combination of the two planes.
+    pub palette: [u32; 4],
+    stack: Vec<u16>,
+    delay_timer: u8,
+    sound_timer: u8,
+    // bit n is set while Chip-8 key n is held, only ever written by the frontend
+    keys: u16,
+    // input as of the start of this frame and the one before, so every
+    // instruction in a frame sees the same keys
+    frame_keys: u16,
+    previous_frame_keys: u16,
+    // the key FX0A saw pressed and is waiting to be released
+    key_wait: Option<u8>,
+    pc: usize,
+    v: [u8; 16],
+    i: u16,
+    audio: Box<dyn AudioSink>,
+    audio_pattern: [u8; 16],
+    pitch: u8,
+    pub update_display: bool,
+    pub quirks: Quirks,
+    pub error_policy: ErrorPolicy,
+    // stopped by an error under ErrorPolicy::Halt
+    halted: bool,
+    // skipped under ErrorPolicy::Log, until the frontend takes them
+    logged_errors: Vec<Chip8Error>,
+    // SUPER-CHIP persistent flag registers (HP-48 RPL user flags)
+    rpl: [u8; 16],
+    exited: bool,
+    // SHA-1 of the loaded rom, so save states can't be loaded into the wrong game
+    rom_hash: [u8; 20],
+    // set by the 60Hz timer tick, consumed by a draw when display_wait is on
+    vblank: bool,
+    // a draw is blocked until the next timer tick, see Quirks::display_wait
+    waiting_for_vblank: bool,
+    // COSMAC VIP machine cycles the last instruction took
+    last_cycles: u32,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chip8 {
+    /// An emulator without sound. Use ```with_audio``` to hear it.
+    pub fn new() -> Self {
+        Self::with_audio(Box::new(NullSink))
+    }
+
+    /// An emulator that plays its sound through ```audio```.
+    pub fn with_audio(audio: Box<dyn AudioSink>) -> Self {
+        let mut em = Self {
+            memory: [0; MEMORY_SIZE],
+            planes: vec![0; LORES_WIDTH * LORES_HEIGHT],
+            display: vec![0; LORES_WIDTH * LORES_HEIGHT],
+            hires: false,
+            selected_planes: 0b01,
+            // plane 1 keeps the original blue
+            palette: [0x000000, 0x0000FF, 0xFF6600, 0xFFFFFF],
+            stack: Vec::with_capacity(16),
+            delay_timer: 0,
+            sound_timer: 0,
+            keys: 0,
+            frame_keys: 0,
+            previous_frame_keys: 0,
+            key_wait: None,
+            pc: PROGRAM_STARTING_ADDR,
+            v: [0; 16],
+            i: 0,
+            audio,
+            audio_pattern: DEFAULT_PATTERN,
+            pitch: DEFAULT_PITCH,
+            update_display: false,
+            quirks: Quirks::default(),
+            error_policy: ErrorPolicy::default(),
+            halted: false,
+            logged_errors: Vec::new(),
+            rpl: [0; 16],
+            exited: false,
+            rom_hash: [0; 20],
+            vblank: false,
+            waiting_for_vblank: false,
+            last_cycles: 0,
+        };
+        em.load_font();
+        em
+    }
+
+    /// Called at 60Hz. The original interpreter did this from the vertical
+    /// blank interrupt, so this is also when a waiting draw may go ahead
+    /// and when the keys are read for the coming frame.
+    pub fn decrement_timers(&mut self) {
+        self.vblank = true;
+        self.previous_frame_keys = self.frame_keys;
+        self.frame_keys = self.keys;
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+            if self.sound_timer == 0 {
+                self.audio.stop_tone();
+            }
+        }
+    }
+
+    /// Runs a single instruction.
+    /// What happens when it fails depends on ```error_policy```.
+    pub fn step(&mut self) -> Result<(), Chip8Error> {
+        if self.exited || self.halted {
+            return Ok(());
+        }
+
+        let pc = self.pc;
+        let v = self.v;
+        self.waiting_for_vblank = false;
+        let result = self.fetch_next_opcode().and_then(|opcode| {
+            let instruction = Instruction::decode(opcode);
+            let result = self.run_instruction(instruction);
+            // a skip over F000 NNNN moves on 6 bytes
+            let skipped = instruction.is_skip() && self.pc != pc + 2;
+            self.last_cycles = cosmac_vip_cycles(instruction, &v, skipped);
+            result
+        });
+        match result {
+            Err(e) => match self.error_policy {
+                ErrorPolicy::Halt => {
+                    self.halted = true;
+                    Err(e)
+                }
+                ErrorPolicy::Log => {
+                    self.logged_errors.push(e);
+                    Ok(())
+                }
+                ErrorPolicy::Ignore => Ok(()),
+            },
+            ok => ok,
+        }
+    }
+
+    /// Ticks the timers and runs a frame's worth of instructions.
+    pub fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        self.decrement_timers();
+        for _ in 0..INSTRUCTIONS_PER_FRAME {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Reads a rom file and copies it into memory at 0x200.
+    pub fn load_rom(&mut self, path: &Path) -> Result<(), Chip8Error> {
+        let rom_buf = fs::read(path)?;
+        self.load_rom_bytes(&rom_buf)
+    }
+
+    /// Copies a rom into memory at 0x200.
+    pub fn load_rom_bytes(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        let max = MEMORY_SIZE - PROGRAM_STARTING_ADDR;
+        if rom.len() > max {
+            return Err(Chip8Error::RomTooLarge {
+                size: rom.len(),
+                max,
+            });
+        }
+
+        self.memory[PROGRAM_STARTING_ADDR..PROGRAM_STARTING_ADDR + rom.len()].copy_from_slice(rom);
+        self.rom_hash = Sha1::digest(rom).into();
+        Ok(())
+    }
+
+    /// SHA-1 of the loaded rom.
+    pub fn rom_hash(&self) -> [u8; 20] {
+        self.rom_hash
+    }
+
+    /// Copies out the full machine state.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            rom_hash: self.rom_hash,
+            memory: self.memory.to_vec(),
+            planes: self.planes.clone(),
+            hires: self.hires,
+            selected_planes: self.selected_planes,
+            stack: self.stack.clone(),
+            v: self.v,
+            i: self.i,
+            pc: self.pc,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            rpl: self.rpl,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            exited: self.exited,
+            vblank: self.vblank,
+            frame_keys: self.frame_keys,
+            previous_frame_keys: self.previous_frame_keys,
+            key_wait: self.key_wait,
+        }
+    }
+
+    /// Puts the machine back into a snapshot's state.
+    /// Fails without changing anything if the snapshot was taken with a different
+    /// rom, or doesn't fit the machine, e.g. has more calls than the stack allows.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        if snapshot.rom_hash != self.rom_hash {
+            return Err(SnapshotError::RomMismatch);
+        }
+        let (width, height) = if snapshot.hires {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else {
+            (LORES_WIDTH, LORES_HEIGHT)
+        };
+        if snapshot.memory.len() != MEMORY_SIZE
+            || snapshot.planes.len() != width * height
+            || snapshot.stack.len() > self.stack_limit()
+        {
+            return Err(SnapshotError::Corrupt);
+        }
+
+        self.memory.copy_from_slice(&snapshot.memory);
+        self.planes = snapshot.planes.clone();
+        self.display = vec![0; width * height];
+        self.hires = snapshot.hires;
+        self.selected_planes = snapshot.selected_planes;
+        self.stack = snapshot.stack.clone();
+        self.v = snapshot.v;
+        self.i = snapshot.i;
+        self.pc = snapshot.pc;
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.rpl = snapshot.rpl;
+        self.audio_pattern = snapshot.audio_pattern;
+        self.pitch = snapshot.pitch;
+        self.exited = snapshot.exited;
+        self.vblank = snapshot.vblank;
+        self.frame_keys = snapshot.frame_keys;
+        self.previous_frame_keys = snapshot.previous_frame_keys;
+        self.key_wait = snapshot.key_wait;
+        self.halted = false;
+
+        self.audio.set_pattern(self.audio_pattern);
+        self.audio.set_pitch(self.pitch);
+        if self.sound_timer > 0 {
+            self.audio.start_tone();
+        } else {
+            self.audio.stop_tone();
+        }
+        self.refresh_display();
+        Ok(())
+    }
+
+    /// Writes a snapshot of the machine to ```path```.
+    pub fn save_state(&self, path: &Path) -> Result<(), SnapshotError> {
+        fs::write(path, self.snapshot().to_bytes())?;
+        Ok(())
+    }
+
+    /// Restores the machine from a snapshot written by ```save_state```.
+    pub fn load_state(&mut self, path: &Path) -> Result<(), SnapshotError> {
+        let snapshot = Snapshot::from_bytes(&fs::read(path)?)?;
+        self.restore(&snapshot)
+    }
+
+    /// Presses or releases Chip-8 key ```key``` (0-F). The rom sees it from
+    /// the next frame on.
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        let bit = 1 << (key & 0xF);
+        if pressed {
+            self.keys |= bit;
+        } else {
+            self.keys &= !bit;
+        }
+    }
+
+    /// Sets every key at once. Bit n is key n.
+    pub fn set_keys(&mut self, keys: u16) {
+        self.keys = keys;
+    }
+
+    /// Keys held as last set by the frontend. Bit n is key n.
+    pub fn keys(&self) -> u16 {
+        self.keys
+    }
+
+    fn is_key_down(&self, key: u8) -> bool {
+        self.frame_keys & (1 << (key & 0xF)) != 0
+    }
+
+    /// The display as 0RGB pixels, ```width()``` by ```height()```.
+    pub fn framebuffer(&self) -> &[u32] {
+        &self.display
+    }
+
+    /// Width of the display in the current resolution.
+    pub fn width(&self) -> usize {
+        if self.hires { HIRES_WIDTH } else { LORES_WIDTH }
+    }
+
+    /// Height of the display in the current resolution.
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_HEIGHT
+        } else {
+            LORES_HEIGHT
+        }
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn v(&self) -> &[u8; 16] {
+        &self.v
+    }
+
+    /// Return addresses, oldest call first.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// The opcode at PC, without fetching it.
+    pub fn peek_opcode(&self) -> u16 {
+        let first_byte = self.memory.get(self.pc).copied().unwrap_or(0);
+        let second_byte = self.memory.get(self.pc + 1).copied().unwrap_or(0);
+        u16::from_be_bytes([first_byte, second_byte])
+    }
+
+    /// Whether the program has stopped the interpreter with 00FD.
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Whether the last instruction was a draw stuck waiting for the next timer tick.
+    pub fn is_waiting_for_vblank(&self) -> bool {
+        self.waiting_for_vblank
+    }
+
+    /// How many machine cycles the last instruction would have taken on a COSMAC VIP.
+    pub fn last_cycles(&self) -> u32 {
+        self.last_cycles
+    }
+
+    /// Whether an error has stopped the interpreter. See ```ErrorPolicy::Halt```.
+    pub fn has_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Errors skipped under ```ErrorPolicy::Log``` since the last call, oldest
+    /// first. Frontends using that policy should print these every frame.
+    pub fn take_logged_errors(&mut self) -> Vec<Chip8Error> {
+        std::mem::take(&mut self.logged_errors)
+    }
+
+    fn load_font(&mut self) {
+        let font = [
+            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+            0x20, 0x60, 0x20, 0x20, 0x70, // 1
+            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+        ];
+
+        // convention to put font data at 0x050 - 0x9F
+        self.memory[FONT_STARTING_ADDR..FONT_STARTING_ADDR + font.len()].copy_from_slice(&font);
+
+        // SUPER-CHIP 8x10 digits, only 0-9 exist
+        let big_font = [
+            0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+            0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+            0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+            0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+            0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+            0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+            0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+            0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+            0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+        ];
+
+        self.memory[BIG_FONT_STARTING_ADDR..BIG_FONT_STARTING_ADDR + big_font.len()]
+            .copy_from_slice(&big_font);
+    }
+
+    // instruction is 2 bytes, so read 2 successive bytes
+    // and combine them into a 16 bit instruction
+    fn fetch_next_opcode(&mut self) -> Result<u16, Chip8Error> {
+        if self.pc + 1 >= MEMORY_SIZE {
+            let pc = self.pc;
+            // wrap around if the error is skipped
+            self.pc = (self.pc + 2) % MEMORY_SIZE;
+            return Err(Chip8Error::PcOutOfBounds { pc });
+        }
+
+        // read current & next instructions
+        let first_byte = self.memory[self.pc] as u16;
+        let second_byte = self.memory[self.pc + 1] as u16;
+
+        // increment PC by 2 to fetch next opcode
+        self.pc += 2;
+
+        // combine into one 16-bit instruction
+        // shift first byte over by 8 to
+        // make room for the next byte
+        Ok((first_byte << 8) | second_byte)
+    }
+
+    /// Address of the instruction being run.
+    fn current_pc(&self) -> usize {
+        self.pc - 2
+    }
+
+    /// Checks ```len``` bytes from ```start``` are inside memory.
+    fn check_memory(&self, start: usize, len: usize) -> Result<(), Chip8Error> {
+        if start + len > MEMORY_SIZE {
+            return Err(Chip8Error::MemoryOutOfBounds {
+                pc: self.current_pc(),
+                addr: start,
+            });
+        }
+        Ok(())
+    }
+
+    fn run_instruction(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
+        match instruction {
+            Instruction::Clear => self.op_00e0(),
+            Instruction::Return => return self.op_00ee(),
+            Instruction::ScrollDown { n } => self.op_00cn(n),
+            Instruction::ScrollUp { n } => self.op_00dn(n),
+            Instruction::ScrollRight => self.op_00fb(),
+            Instruction::ScrollLeft => self.op_00fc(),
+            Instruction::Exit => self.op_00fd(),
+            Instruction::Lores => self.op_00fe(),
+            Instruction::Hires => self.op_00ff(),
+            Instruction::Sys { .. } => {} // 0NNN, ignore this instruction
+            Instruction::Jump { nnn } => self.op_1nnn(nnn),
+            Instruction::Call { nnn } => return self.op_2nnn(nnn),
+            Instruction::SkipIfEqual { x, nn } => self.op_3xnn(x, nn),
+            Instruction::SkipIfNotEqual { x, nn } => self.op_4xnn(x, nn),
+            Instruction::SkipIfRegistersEqual { x, y } => self.op_5xy0(x, y),
+            Instruction::SaveRange { x, y } => return self.op_5xy2(x, y),
+            Instruction::LoadRange { x, y } => return self.op_5xy3(x, y),
+            Instruction::Set { x, nn } => self.op_6xnn(x, nn),
+            Instruction::Add { x, nn } => self.op_7xnn(x, nn),
+            Instruction::Copy { x, y } => self.op_8xy0(x, y),
+            Instruction::Or { x, y } => self.op_8xy1(x, y),
+            Instruction::And { x, y } => self.op_8xy2(x, y),
+            Instruction::Xor { x, y } => self.op_8xy3(x, y),
+            Instruction::AddRegisters { x, y } => self.op_8xy4(x, y),
+            Instruction::Subtract { x, y } => self.op_8xy5(x, y),
+            Instruction::ShiftRight { x, y } => self.op_8xy6(x, y),
+            Instruction::SubtractReversed { x, y } => self.op_8xy7(x, y),
+            Instruction::ShiftLeft { x, y } => self.op_8xye(x, y),
+            Instruction::SkipIfRegistersNotEqual { x, y } => self.op_9xy0(x, y),
+            Instruction::SetIndex { nnn } => self.op_annn(nnn),
+            Instruction::JumpWithOffset { x, nnn } => self.op_bnnn(x, nnn),
+            Instruction::Random { x, nn } => self.op_cxnn(nn, x),
+            Instruction::Draw { x, y, n } => return self.op_dxyn(n, x, y),
+            Instruction::SkipIfKey { x } => self.op_ex9e(x),
+            Instruction::SkipIfNotKey { x } => self.op_exa1(x),
+            Instruction::LoadLongIndex => return self.op_f000(),
+            Instruction::SelectPlanes { n } => self.op_fn01(n),
+            Instruction::LoadAudioPattern => return self.op_f002(),
+            Instruction::GetDelayTimer { x } => self.op_fx07(x),
+            Instruction::WaitForKey { x } => self.op_fx0a(x),
+            Instruction::SetDelayTimer { x } => self.op_fx15(x),
+            Instruction::SetSoundTimer { x } => self.op_fx18(x),
+            Instruction::AddToIndex { x } => self.op_fx1e(x),
+            Instruction::Font { x } => self.op_fx29(x),
+            Instruction::BigFont { x } => self.op_fx30(x),
+            Instruction::Bcd { x } => return self.op_fx33(x),
+            Instruction::SetPitch { x } => self.op_fx3a(x),
+            Instruction::Store { x } => return self.op_fx55(x),
+            Instruction::Load { x } => return self.op_fx65(x),
+            Instruction::SaveFlags { x } => self.op_fx75(x),
+            Instruction::LoadFlags { x } => self.op_fx85(x),
+            Instruction::Unknown { opcode } => return self.op_unknown(opcode),
+        };
+        Ok(())
+    }
+
+    /// Clears the selected planes of the display
+    fn op_00e0(&mut self) {
+        let selected = self.selected_planes;
+        self.planes.iter_mut().for_each(|pixel| *pixel &= !selected);
+        self.refresh_display();
+    }
+
+    /// Recomposites the planes into colors and flags the display for redraw.
+    fn refresh_display(&mut self) {
+        for (pixel, planes) in self.display.iter_mut().zip(self.planes.iter()) {
+            *pixel = self.palette[*planes as usize & 0b11];
+        }
+        self.update_display = true;
+    }
+
+    /// Moves the selected planes by ```dx```, ```dy``` pixels.
+    /// Pixels scrolled in from outside the screen are off.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let width = self.width() as isize;
+        let height = self.height() as isize;
+        let selected = self.selected_planes;
+        let previous = self.planes.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (src_x, src_y) = (x - dx, y - dy);
+                let moved = if (0..width).contains(&src_x) && (0..height).contains(&src_y) {
+                    previous[(src_y * width + src_x) as usize] & selected
+                } else {
+                    0
+                };
+
+                let idx = (y * width + x) as usize;
+                self.planes[idx] = (previous[idx] & !selected) | moved;
+            }
+        }
+        self.refresh_display();
+    }
+
+    // Computer specific instruction - not needed
+    // fn op_0nnn() {}
+
+    /// Scrolls the display down by ```n``` pixels.
+    fn op_00cn(&mut self, n: usize) {
+        self.scroll(0, n as isize);
+    }
+
+    /// Scrolls the display up by ```n``` pixels.
+    fn op_00dn(&mut self, n: usize) {
+        self.scroll(0, -(n as isize));
+    }
+
+    /// Scrolls the display right by 4 pixels.
+    fn op_00fb(&mut self) {
+        self.scroll(4, 0);
+    }
+
+    /// Scrolls the display left by 4 pixels.
+    fn op_00fc(&mut self) {
+        self.scroll(-4, 0);
+    }
+
+    /// Exits the interpreter. Nothing else runs after this.
+    fn op_00fd(&mut self) {
+        self.exited = true;
+    }
+
+    /// Switches to 64x32 low resolution and clears the display.
+    fn op_00fe(&mut self) {
+        self.set_resolution(false);
+    }
+
+    /// Switches to 128x64 high resolution and clears the display.
+    fn op_00ff(&mut self) {
+        self.set_resolution(true);
+    }
+
+    fn set_resolution(&mut self, hires: bool) {
+        self.hires = hires;
+        self.planes = vec![0; self.width() * self.height()];
+        self.display = vec![0; self.width() * self.height()];
+        self.refresh_display();
+    }
+
+    /// Sets PC to ```nnn```,
+    /// This function does not increment the PC.
+    fn op_1nnn(&mut self, nnn: u16) {
+        self.pc = nnn as usize;
+    }
+
+    /// Return from subroutine by setting pc to popped stack address.
+    fn op_00ee(&mut self) -> Result<(), Chip8Error> {
+        self.pc = self.pop_stack()? as usize;
+        Ok(())
+    }
+
+    /// Saves current pc to stack before setting pc to ```nnn```
+    fn op_2nnn(&mut self, nnn: u16) -> Result<(), Chip8Error> {
+        self.push_stack(self.pc as u16)?;
+        self.pc = nnn as usize;
+        Ok(())
+    }
+
+    /// Calls allowed before 2NNN overflows the stack, which a memory mapped
+    /// stack caps at what fits in the VIP's stack area.
+    fn stack_limit(&self) -> usize {
+        if self.quirks.memory_mapped_stack {
+            self.quirks.stack_depth.min(MAX_MEMORY_MAPPED_STACK_DEPTH)
+        } else {
+            self.quirks.stack_depth
+        }
+    }
+
+    /// Pushes a return address, failing once ```stack_limit``` calls deep.
+    fn push_stack(&mut self, addr: u16) -> Result<(), Chip8Error> {
+        if self.stack.len() >= self.stack_limit() {
+            return Err(Chip8Error::StackOverflow {
+                pc: self.current_pc(),
+            });
+        }
+
+        if self.quirks.memory_mapped_stack {
+            let slot = MEMORY_STACK_ADDR + self.stack.len() * 2;
+            self.memory[slot..slot + 2].copy_from_slice(&addr.to_be_bytes());
+        }
+        self.stack.push(addr);
+        Ok(())
+    }
+
+    /// Pops a return address. With a memory mapped stack,
+    /// memory is the source of truth in case the program changed it.
+    fn pop_stack(&mut self) -> Result<u16, Chip8Error> {
+        let addr = self.stack.pop().ok_or(Chip8Error::StackUnderflow {
+            pc: self.current_pc(),
+        })?;
+
+        if self.quirks.memory_mapped_stack {
+            let slot = MEMORY_STACK_ADDR + self.stack.len() * 2;
+            let Some(&[high, low]) = self.memory.get(slot..slot + 2) else {
+                return Err(Chip8Error::MemoryOutOfBounds {
+                    pc: self.current_pc(),
+                    addr: slot,
+                });
+            };
+            return Ok(u16::from_be_bytes([high, low]));
+        }
+        Ok(addr)
+    }
+
+    /// Skips the next instruction. F000 NNNN is 4 bytes long so it is skipped whole.
+    fn skip_next_instruction(&mut self) {
+        self.pc += if self.peek_opcode() == 0xF000 { 4 } else { 2 };
+    }
+
+    /// Skips one instruction if V```x``` is equal to ```nn```
+    fn op_3xnn(&mut self, x: usize, nn: u8) {
+        if self.v[x] == nn {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// Skips one instruction if V```x``` is not equal to ```nn```
+    fn op_4xnn(&mut self, x: usize, nn: u8) {
+        if self.v[x] != nn {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// Skips one instruction if
+    /// values in V```x``` and V```y``` are equal
+    fn op_5xy0(&mut self, x: usize, y: usize) {
+        if self.v[x] == self.v[y] {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// Registers V```x``` to V```y``` in order, counting down if ```x``` is larger.
+    fn register_range(x: usize, y: usize) -> Vec<usize> {
+        if x <= y {
+            (x..=y).collect()
+        } else {
+            (y..=x).rev().collect()
+        }
+    }
+
+    /// Stores V```x``` to V```y``` in memory starting at i.
+    /// i is left unchanged.
+    fn op_5xy2(&mut self, x: usize, y: usize) -> Result<(), Chip8Error> {
+        self.check_memory(self.i as usize, x.abs_diff(y) + 1)?;
+        for (offset, register) in Self::register_range(x, y).into_iter().enumerate() {
+            self.memory[self.i as usize + offset] = self.v[register];
+        }
+        Ok(())
+    }
+
+    /// Loads V```x``` to V```y``` from memory starting at i.
+    /// i is left unchanged.
+    fn op_5xy3(&mut self, x: usize, y: usize) -> Result<(), Chip8Error> {
+        self.check_memory(self.i as usize, x.abs_diff(y) + 1)?;
+        for (offset, register) in Self::register_range(x, y).into_iter().enumerate() {
+            self.v[register] = self.memory[self.i as usize + offset];
+        }
+        Ok(())
+    }
+
+    /// Looks up register ```x``` and sets its value to ```nn```
+    fn op_6xnn(&mut self, x: usize, nn: u8) {
+        self.v[x] = nn;
+    }
+
+    /// Add the value ```nn``` to VX.
+    fn op_7xnn(&mut self, x: usize, nn: u8) {
+        let result = self.v[x].overflowing_add(nn);
+        self.v[x] = result.0;
+    }
+
+    /// Sets V```x``` to value of V```y```
+    fn op_8xy0(&mut self, x: usize, y: usize) {
+        self.v[x] = self.v[y];
+    }
+
+    /// Sets V```x``` to bitwise OR of V```x``` and V```y```
+    /// Then resets vF to 0 (VIP quirk).
+    fn op_8xy1(&mut self, x: usize, y: usize) {
+        self.v[x] |= self.v[y];
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
+    }
+
+    /// Sets V```x``` to bitwise AND of V```x``` and V```y```
+    /// Then resets vF to 0 (VIP quirk).
+    fn op_8xy2(&mut self, x: usize, y: usize) {
+        self.v[x] &= self.v[y];
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
+    }
+
+    /// Sets V```x``` to bitwise XOR of V```x``` and V```y```
+    /// Then resets vF to 0 (VIP quirk).
+    fn op_8xy3(&mut self, x: usize, y: usize) {
+        self.v[x] ^= self.v[y];
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
+    }
+
+    /// Sets V```x``` to the sum of V```x``` and V```y```
+    /// VF is set to 1 if the addition would overflow, 0 otherwise.
+    fn op_8xy4(&mut self, x: usize, y: usize) {
+        let result = self.v[x].overflowing_add(self.v[y]);
+        self.v[x] = result.0;
+
+        // assign it directly because I'm lazy
+        self.v[0xF] = result.1 as u8;
+    }
+
+    /// sets V```x``` to the difference of V```x``` and V```y```.
+    /// Sets VF to 0 if the subtraction would overflow, 1 otherwise.
+    fn op_8xy5(&mut self, x: usize, y: usize) {
+        let result = self.v[x].overflowing_sub(self.v[y]);
+        self.v[x] = result.0;
+
+        self.v[0xF] = if result.1 { 0 } else { 1 }
+    }
+
+    /// Stores V```y``` into V```x``` (shift quirk), right shifts V```x```,
+    /// and sets VF to the LSB that is shifted out.
+    fn op_8xy6(&mut self, x: usize, y: usize) {
+        if self.quirks.shift_uses_vy {
+            self.v[x] = self.v[y];
+        }
+
+        // Set vF to bit that is shifted out
+        let lsb = 1 & self.v[x];
+        self.v[x] >>= 1;
+        self.v[0xF] = lsb;
+    }
+
+    /// sets V```x``` to difference V```y``` and V```x```.
+    /// Sets VF to 0 if the subtraction would overflow, 1 otherwise.
+    fn op_8xy7(&mut self, x: usize, y: usize) {
+        let result = self.v[y].overflowing_sub(self.v[x]);
+        self.v[x] = result.0;
+
+        self.v[0xF] = if result.1 { 0 } else { 1 };
+    }
+
+    /// Stores V```y``` into V```x``` (shift quirk), left shifts V```x```.
+    /// Sets VF to the MSB that was shifted out.
+    fn op_8xye(&mut self, x: usize, y: usize) {
+        if self.quirks.shift_uses_vy {
+            self.v[x] = self.v[y];
+        }
+
+        let msb = (0b1000_0000 & self.v[x]) >> 7;
+        self.v[x] <<= 1;
+        self.v[0xF] = msb;
+    }
+
+    /// Skips one instruction if
+    /// V```x``` and V```y``` are not equal.
+    fn op_9xy0(&mut self, x: usize, y: usize) {
+        if self.v[x] != self.v[y] {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// Sets the index register I to ```nnn```
+    fn op_annn(&mut self, nnn: u16) {
+        self.i = nnn;
+    }
+
+    /// Jumps to address ```nnn``` plus the value in register V[0].
+    /// With the jump quirk, this is BXNN and uses V```x``` instead.
+    fn op_bnnn(&mut self, x: usize, nnn: u16) {
+        let offset_register = if self.quirks.jump_uses_vx { x } else { 0 };
+        let jump_destination = nnn + self.v[offset_register] as u16;
+        self.op_1nnn(jump_destination);
+    }
+
+    /// Generates a random number, binary ANDs it with ```nn```,
+    /// and puts the result in V```x```.
+    fn op_cxnn(&mut self, nn: u8, x: usize) {
+        let num = rand::random::<u8>();
+        self.v[x] = num & nn;
+    }
+
+    /// Draws a sprite to the screen.
+    /// Sprites are clipped at the edges or wrap around depending on the quirks.
+    /// DXY0 draws a 16x16 sprite made of 2 byte rows.
+    fn op_dxyn(&mut self, n: usize, x: usize, y: usize) -> Result<(), Chip8Error> {
+        if self.quirks.display_wait {
+            if !self.vblank {
+                // try again until the next timer tick
+                self.pc -= 2;
+                self.waiting_for_vblank = true;
+                return Ok(());
+            }
+            self.vblank = false;
+        }
+
+        let width = self.width();
+        let height = self.height();
+        let x_coord = self.v[x] as usize % width;
+        let y_coord = self.v[y] as usize % height;
+        self.v[0xF] = 0;
+
+        let (rows, sprite_width) = if n == 0 { (16, 16) } else { (n, 8) };
+        let sprite_size = rows * sprite_width / 8;
+        let plane_count = self.selected_planes.count_ones() as usize;
+        self.check_memory(self.i as usize, sprite_size * plane_count)?;
+
+        // each selected plane gets its own sprite, one after the other in memory
+        let mut sprite_addr = self.i as usize;
+        for plane in [0b01, 0b10] {
+            if self.selected_planes & plane == 0 {
+                continue;
+            }
+
+            for row in 0..rows {
+                if self.quirks.clip_sprites && y_coord + row >= height {
+                    break;
+                }
+
+                // left align the row in 16 bits so both sprite sizes are read the same way
+                let sprite_row = if sprite_width == 16 {
+                    let addr = sprite_addr + row * 2;
+                    u16::from_be_bytes([self.memory[addr], self.memory[addr + 1]])
+                } else {
+                    (self.memory[sprite_addr + row] as u16) << 8
+                };
+
+                for bit in 0..sprite_width {
+                    if self.quirks.clip_sprites && x_coord + bit >= width {
+                        break;
+                    }
+
+                    let sprite_row_pixel = (sprite_row >> (15 - bit)) & 1;
+
+                    if sprite_row_pixel == 1 {
+                        let pixel_x = (x_coord + bit) % width;
+                        let pixel_y = (y_coord + row) % height;
+                        let display_idx = pixel_y * width + pixel_x;
+                        if self.planes[display_idx] & plane != 0 {
+                            self.v[0xF] = 1; // set VF if pixel was already on - collision
+                        }
+                        self.planes[display_idx] ^= plane;
+                    }
+                }
+            }
+            sprite_addr += sprite_size;
+        }
+        self.refresh_display();
+        Ok(())
+    }
+
+    /// Skips one instruction if key in value V```x``` is pressed.
+    /// checks if key is currently being held.
+    fn op_ex9e(&mut self, x: usize) {
+        if self.is_key_down(self.v[x]) {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// Skips one instruction if key in value V```x``` is not pressed.
+    /// Executes if it's currently being held.
+    fn op_exa1(&mut self, x: usize) {
+        if !self.is_key_down(self.v[x]) {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// Loads the 16 bit address following this instruction into i.
+    /// This is the only 4 byte instruction.
+    fn op_f000(&mut self) -> Result<(), Chip8Error> {
+        if self.pc + 1 >= MEMORY_SIZE {
+            return Err(Chip8Error::PcOutOfBounds { pc: self.pc });
+        }
+        self.i = u16::from_be_bytes([self.memory[self.pc], self.memory[self.pc + 1]]);
+        self.pc += 2;
+        Ok(())
+    }
+
+    /// Selects which planes are drawn to, cleared & scrolled.
+    /// ```n``` is a bitmask of the planes.
+    fn op_fn01(&mut self, n: usize) {
+        self.selected_planes = n as u8 & 0b11;
+    }
+
+    /// Loads the 16 byte audio pattern starting at i.
+    fn op_f002(&mut self) -> Result<(), Chip8Error> {
+        let start = self.i as usize;
+        self.check_memory(start, 16)?;
+        self.audio_pattern
+            .copy_from_slice(&self.memory[start..start + 16]);
+        self.audio.set_pattern(self.audio_pattern);
+        Ok(())
+    }
+
+    /// Sets the audio pattern playback pitch to V```x```.
+    fn op_fx3a(&mut self, x: usize) {
+        self.pitch = self.v[x];
+        self.audio.set_pitch(self.pitch);
+    }
+
+    /// sets V```x``` to current delay timer value.
+    fn op_fx07(&mut self, x: usize) {
+        self.v[x] = self.delay_timer;
+    }
+
+    /// sets delay timer to the value in V```x```.
+    fn op_fx15(&mut self, x: usize) {
+        self.delay_timer = self.v[x];
+    }
+
+    /// sets the sound timer to the value in V```x```.
+    /// The tone plays until the timer counts down to 0.
+    fn op_fx18(&mut self, x: usize) {
+        let was_playing = self.sound_timer > 0;
+        self.sound_timer = self.v[x];
+        match (was_playing, self.sound_timer > 0) {
+            (false, true) => self.audio.start_tone(),
+            (true, false) => self.audio.stop_tone(),
+            _ => {}
+        }
+    }
+
+    /// Adds value of V```x``` to index register.
+    fn op_fx1e(&mut self, x: usize) {
+        self.i = self.i.wrapping_add(self.v[x] as u16);
+    }
+
+    /// Blocks until a key is pressed and released, like the COSMAC VIP,
+    /// then stores it in V```x```.
+    fn op_fx0a(&mut self, x: usize) {
+        match self.key_wait {
+            Some(key) if !self.is_key_down(key) => {
+                self.v[x] = key;
+                self.key_wait = None;
+                return;
+            }
+            Some(_) => {}
+            None => {
+                // only keys that went down this frame, not ones held from before
+                let pressed = self.frame_keys & !self.previous_frame_keys;
+                if pressed != 0 {
+                    // lowest key wins
+                    self.key_wait = Some(pressed.trailing_zeros() as u8);
+                }
+            }
+        }
+        self.pc -= 2;
+    }
+
+    /// Sets index register to the address of hexadecimal character in V```x```.
+    fn op_fx29(&mut self, x: usize) {
+        // isolate lower nibble to get needed char
+        let num = 0xf & self.v[x];
+
+        // multiply by 5 since each char is 5 bytes apart to get offset & font's starting idx
+        self.i = (FONT_STARTING_ADDR + (5 * num as usize)) as u16;
+    }
+
+    /// Sets index register to the address of the large 8x10
+    /// SUPER-CHIP digit in V```x```.
+    fn op_fx30(&mut self, x: usize) {
+        let num = 0xf & self.v[x];
+
+        // each large digit is 10 bytes
+        self.i = (BIG_FONT_STARTING_ADDR + (10 * num as usize)) as u16;
+    }
+
+    /// Convert value in V```x``` to three decimal digits
+    /// and store them in memory at address in index register i.
+    fn op_fx33(&mut self, x: usize) -> Result<(), Chip8Error> {
+        // since any given number in v is u8 (<= 255), we only need to modulo 3 times
+        let mut num = self.v[x];
+        let address = self.i as usize;
+        self.check_memory(address, 3)?;
+
+        // 156 -> 1 in i, 5 in i + 1, 6 in i + 2
+        // num will be truncated toward zero
+        self.memory[address + 2] = num % 10;
+        num /= 10;
+
+        self.memory[address + 1] = num % 10;
+        num /= 10;
+
+        self.memory[address] = num % 10;
+        Ok(())
+    }
+
+    /// Reads values in V registers and stores them in
+    /// successive memory addresses starting from i
+    fn op_fx55(&mut self, x: usize) -> Result<(), Chip8Error> {
+        self.check_memory(self.i as usize, x + 1)?;
+        for i in 0..x + 1 {
+            self.memory[self.i as usize + i] = self.v[i];
+        }
+
+        if self.quirks.load_store_increments_i {
+            self.i = self.i.wrapping_add(x as u16 + 1);
+        }
+        Ok(())
+    }
+
+    /// Takes values stored successively in memory
+    /// starting from i and then loads them
+    /// into V registers
+    fn op_fx65(&mut self, x: usize) -> Result<(), Chip8Error> {
+        self.check_memory(self.i as usize, x + 1)?;
+        for i in 0..x + 1 {
+            self.v[i] = self.memory[self.i as usize + i];
+        }
+
+        if self.quirks.load_store_increments_i {
+            self.i = self.i.wrapping_add(x as u16 + 1);
+        }
+        Ok(())
+    }
+
+    /// Stores V0 to V```x``` in the RPL user flags.
+    fn op_fx75(&mut self, x: usize) {
+        self.rpl[..=x].copy_from_slice(&self.v[..=x]);
+    }
+
+    /// Loads V0 to V```x``` from the RPL user flags.
+    fn op_fx85(&mut self, x: usize) {
+        self.v[..=x].copy_from_slice(&self.rpl[..=x]);
+    }
+
+    fn op_unknown(&self, opcode: u16) -> Result<(), Chip8Error> {
+        Err(Chip8Error::UnknownOpcode {
+            pc: self.current_pc(),
+            opcode,
+        })
+    }
+}
@@ -0,0 +1,122 @@
+use super::Chip8;
+use crate::{audio::AudioSink, error::Chip8Error, instruction::Instruction, quirks::Quirks};
+
+/// Sets up a machine in any state for testing single instructions.
+///
+/// ```ignore
+/// let mut em = Chip8Builder::new()
+///     .with_registers(&[0x12, 0x34])
+///     .with_memory(0x300, &[0xFF])
+///     .with_pc(0x250)
+///     .build();
+/// em.execute(0x8014)?;
+/// ```
+pub(crate) struct Chip8Builder {
+    // boxed since the machine's memory is too big to move around the stack much
+    em: Box<Chip8>,
+}
+
+impl Chip8Builder {
+    /// A freshly reset machine with the default quirks.
+    pub fn new() -> Self {
+        Self {
+            em: Box::new(Chip8::new()),
+        }
+    }
+
+    /// Sets V0 onwards to ```values```.
+    pub fn with_registers(mut self, values: &[u8]) -> Self {
+        self.em.v[..values.len()].copy_from_slice(values);
+        self
+    }
+
+    pub fn with_register(mut self, x: usize, value: u8) -> Self {
+        self.em.v[x] = value;
+        self
+    }
+
+    pub fn with_i(mut self, i: u16) -> Self {
+        self.em.i = i;
+        self
+    }
+
+    pub fn with_pc(mut self, pc: usize) -> Self {
+        self.em.pc = pc;
+        self
+    }
+
+    pub fn with_memory(mut self, addr: usize, bytes: &[u8]) -> Self {
+        self.em.memory[addr..addr + bytes.len()].copy_from_slice(bytes);
+        self
+    }
+
+    /// Return addresses, oldest call first.
+    pub fn with_stack(mut self, stack: &[u16]) -> Self {
+        self.em.stack = stack.to_vec();
+        self
+    }
+
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.em.quirks = quirks;
+        self
+    }
+
+    pub fn with_timers(mut self, delay: u8, sound: u8) -> Self {
+        self.em.delay_timer = delay;
+        self.em.sound_timer = sound;
+        self
+    }
+
+    /// Holds ```keys``` as if they went down at the start of this frame.
+    pub fn with_keys(mut self, keys: u16) -> Self {
+        self.em.keys = keys;
+        self.em.frame_keys = keys;
+        self
+    }
+
+    /// Lets a draw go ahead under ```Quirks::display_wait```.
+    pub fn with_vblank(mut self) -> Self {
+        self.em.vblank = true;
+        self
+    }
+
+    pub fn with_hires(mut self) -> Self {
+        self.em.set_resolution(true);
+        self
+    }
+
+    pub fn with_selected_planes(mut self, planes: u8) -> Self {
+        self.em.selected_planes = planes;
+        self
+    }
+
+    /// Turns on pixels in the given planes.
+    pub fn with_pixels(mut self, planes: u8, pixels: &[(usize, usize)]) -> Self {
+        let width = self.em.width();
+        for (x, y) in pixels {
+            self.em.planes[y * width + x] |= planes;
+        }
+        self.em.refresh_display();
+        self
+    }
+
+    pub fn with_audio(mut self, audio: Box<dyn AudioSink>) -> Self {
+        self.em.audio = audio;
+        self
+    }
+
+    /// The machine, still boxed.
+    pub fn build(self) -> Box<Chip8> {
+        self.em
+    }
+}
+
+impl Chip8 {
+    /// Runs ```opcode``` as if it had been fetched from PC, leaving the
+    /// rest of memory alone. Errors are returned whatever the error policy.
+    pub(crate) fn execute(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+        self.memory[self.pc..self.pc + 2].copy_from_slice(&opcode.to_be_bytes());
+        let opcode = self.fetch_next_opcode()?;
+        self.run_instruction(Instruction::decode(opcode))
+    }
+}
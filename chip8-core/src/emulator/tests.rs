@@ -0,0 +1,992 @@
+use std::fmt::Debug;
+
+use super::{Chip8, builder::Chip8Builder};
+use crate::{
+    audio::{AudioEvent, RecordingSink},
+    error::{Chip8Error, ErrorPolicy},
+    quirks::{Platform, Quirks},
+};
+
+/// One opcode run on a machine set up by ```machine```.
+struct Case {
+    name: &'static str,
+    opcode: u16,
+    machine: Chip8Builder,
+    expect: Expect,
+}
+
+/// What should change. Anything not mentioned is expected to stay as it
+/// was, and PC to move on to the next instruction.
+#[derive(Default)]
+struct Expect {
+    registers: Vec<(usize, u8)>,
+    i: Option<u16>,
+    pc: Option<usize>,
+    stack: Option<Vec<u16>>,
+    memory: Vec<(usize, Vec<u8>)>,
+    // (x, y, planes) of every pixel that's on
+    pixels: Option<Vec<(usize, usize, u8)>>,
+    hires: Option<bool>,
+    exited: bool,
+    selected_planes: Option<u8>,
+    timers: Option<(u8, u8)>,
+    audio: Vec<AudioEvent>,
+    error: Option<Chip8Error>,
+}
+
+impl Expect {
+    fn v(mut self, x: usize, value: u8) -> Self {
+        self.registers.push((x, value));
+        self
+    }
+
+    fn vf(self, value: u8) -> Self {
+        self.v(0xF, value)
+    }
+
+    fn i(self, i: u16) -> Self {
+        Self { i: Some(i), ..self }
+    }
+
+    fn pc(self, pc: usize) -> Self {
+        Self {
+            pc: Some(pc),
+            ..self
+        }
+    }
+
+    fn stack(self, stack: &[u16]) -> Self {
+        Self {
+            stack: Some(stack.to_vec()),
+            ..self
+        }
+    }
+
+    fn memory(mut self, addr: usize, bytes: &[u8]) -> Self {
+        self.memory.push((addr, bytes.to_vec()));
+        self
+    }
+
+    /// Exactly these pixels are on, in plane 1.
+    fn pixels(self, pixels: &[(usize, usize)]) -> Self {
+        let pixels: Vec<_> = pixels.iter().map(|&(x, y)| (x, y, 1)).collect();
+        self.plane_pixels(&pixels)
+    }
+
+    fn plane_pixels(self, pixels: &[(usize, usize, u8)]) -> Self {
+        let mut pixels = pixels.to_vec();
+        pixels.sort_by_key(|&(x, y, _)| (y, x));
+        Self {
+            pixels: Some(pixels),
+            ..self
+        }
+    }
+
+    fn hires(self, hires: bool) -> Self {
+        Self {
+            hires: Some(hires),
+            ..self
+        }
+    }
+
+    fn exited(self) -> Self {
+        Self {
+            exited: true,
+            ..self
+        }
+    }
+
+    fn selected_planes(self, planes: u8) -> Self {
+        Self {
+            selected_planes: Some(planes),
+            ..self
+        }
+    }
+
+    fn timers(self, delay: u8, sound: u8) -> Self {
+        Self {
+            timers: Some((delay, sound)),
+            ..self
+        }
+    }
+
+    fn audio(self, audio: &[AudioEvent]) -> Self {
+        Self {
+            audio: audio.to_vec(),
+            ..self
+        }
+    }
+
+    fn error(self, error: Chip8Error) -> Self {
+        Self {
+            error: Some(error),
+            ..self
+        }
+    }
+}
+
+fn case(name: &'static str, opcode: u16, machine: Chip8Builder, expect: Expect) -> Case {
+    Case {
+        name,
+        opcode,
+        machine,
+        expect,
+    }
+}
+
+fn machine() -> Chip8Builder {
+    Chip8Builder::new()
+}
+
+fn expect() -> Expect {
+    Expect::default()
+}
+
+fn vip() -> Quirks {
+    Platform::CosmacVip.quirks()
+}
+
+fn schip() -> Quirks {
+    Platform::SuperChip.quirks()
+}
+
+fn xochip() -> Quirks {
+    Platform::XoChip.quirks()
+}
+
+fn lit_pixels(em: &Chip8) -> Vec<(usize, usize, u8)> {
+    let width = em.width();
+    em.planes
+        .iter()
+        .enumerate()
+        .filter(|(_, planes)| **planes != 0)
+        .map(|(idx, planes)| (idx % width, idx / width, *planes))
+        .collect()
+}
+
+fn compare<T: PartialEq + Debug>(failures: &mut Vec<String>, what: &str, actual: T, expected: T) {
+    if actual != expected {
+        failures.push(format!("{what} is {actual:X?}, expected {expected:X?}"));
+    }
+}
+
+/// Runs a case, returning what didn't match.
+fn run(case: Case) -> Vec<String> {
+    let sink = RecordingSink::new();
+    let mut em = case.machine.with_audio(Box::new(sink.clone())).build();
+    let expect = case.expect;
+
+    let mut v = em.v;
+    for (x, value) in expect.registers {
+        v[x] = value;
+    }
+    let i = expect.i.unwrap_or(em.i);
+    let pc = expect.pc.unwrap_or(em.pc + 2);
+    let stack = expect.stack.unwrap_or_else(|| em.stack.clone());
+    let pixels = expect.pixels.unwrap_or_else(|| lit_pixels(&em));
+    let hires = expect.hires.unwrap_or(em.hires);
+    let selected_planes = expect.selected_planes.unwrap_or(em.selected_planes);
+    let timers = expect.timers.unwrap_or((em.delay_timer, em.sound_timer));
+
+    let result = em.execute(case.opcode);
+
+    let mut failures = Vec::new();
+    compare(
+        &mut failures,
+        "error",
+        result.err().map(|e| format!("{e:?}")),
+        expect.error.map(|e| format!("{e:?}")),
+    );
+    for (x, (actual, expected)) in em.v.iter().zip(v).enumerate() {
+        compare(&mut failures, &format!("V{x:X}"), *actual, expected);
+    }
+    compare(&mut failures, "I", em.i, i);
+    compare(&mut failures, "PC", em.pc, pc);
+    compare(&mut failures, "stack", em.stack.clone(), stack);
+    for (addr, bytes) in expect.memory {
+        let actual = em.memory[addr..addr + bytes.len()].to_vec();
+        compare(
+            &mut failures,
+            &format!("memory at {addr:#X}"),
+            actual,
+            bytes,
+        );
+    }
+    compare(&mut failures, "lit pixels", lit_pixels(&em), pixels);
+    compare(&mut failures, "hires", em.hires, hires);
+    compare(&mut failures, "exited", em.exited, expect.exited);
+    compare(
+        &mut failures,
+        "selected planes",
+        em.selected_planes,
+        selected_planes,
+    );
+    compare(
+        &mut failures,
+        "timers",
+        (em.delay_timer, em.sound_timer),
+        timers,
+    );
+    compare(&mut failures, "audio", sink.events(), expect.audio);
+
+    failures
+        .into_iter()
+        .map(|failure| format!("{} ({:04X}): {failure}", case.name, case.opcode))
+        .collect()
+}
+
+/// Runs every case and reports all that failed at once.
+fn check(cases: Vec<Case>) {
+    let count = cases.len();
+    let failures: Vec<String> = cases.into_iter().flat_map(run).collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {count} checks failed:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn flow() {
+    check(vec![
+        case("0NNN is ignored", 0x0123, machine(), expect()),
+        case("1NNN jumps", 0x1ABC, machine(), expect().pc(0xABC)),
+        case(
+            "2NNN calls",
+            0x2ABC,
+            machine(),
+            expect().pc(0xABC).stack(&[0x202]),
+        ),
+        case(
+            "2NNN returns to the instruction after it",
+            0x2ABC,
+            machine().with_pc(0x250),
+            expect().pc(0xABC).stack(&[0x252]),
+        ),
+        case(
+            "2NNN overflows past the VIP's depth",
+            0x2ABC,
+            machine().with_stack(&[0x300; 12]),
+            expect().error(Chip8Error::StackOverflow { pc: 0x200 }),
+        ),
+        case(
+            "2NNN nests deeper on SUPER-CHIP",
+            0x2ABC,
+            machine().with_quirks(schip()).with_stack(&[0x300; 12]),
+            expect().pc(0xABC).stack(&[
+                0x300, 0x300, 0x300, 0x300, 0x300, 0x300, 0x300, 0x300, 0x300, 0x300, 0x300, 0x300,
+                0x202,
+            ]),
+        ),
+        case(
+            "2NNN writes a memory mapped stack",
+            0x2ABC,
+            machine().with_quirks(Quirks {
+                memory_mapped_stack: true,
+                ..vip()
+            }),
+            expect()
+                .pc(0xABC)
+                .stack(&[0x202])
+                .memory(0xEA0, &[0x02, 0x02]),
+        ),
+        case(
+            "00EE returns",
+            0x00EE,
+            machine().with_stack(&[0x250, 0x300]),
+            expect().pc(0x300).stack(&[0x250]),
+        ),
+        case(
+            "00EE underflows",
+            0x00EE,
+            machine(),
+            expect().error(Chip8Error::StackUnderflow { pc: 0x200 }),
+        ),
+        case(
+            "00EE reads a memory mapped stack",
+            0x00EE,
+            machine()
+                .with_quirks(Quirks {
+                    memory_mapped_stack: true,
+                    ..vip()
+                })
+                .with_stack(&[0x300])
+                .with_memory(0xEA0, &[0x04, 0x56]),
+            expect().pc(0x456).stack(&[]),
+        ),
+        case(
+            "BNNN adds V0",
+            0xB210,
+            machine().with_registers(&[4, 0, 8]),
+            expect().pc(0x214),
+        ),
+        case(
+            "BXNN adds VX with the jump quirk",
+            0xB210,
+            machine().with_quirks(schip()).with_registers(&[4, 0, 8]),
+            expect().pc(0x218),
+        ),
+        case("00FD exits", 0x00FD, machine(), expect().exited()),
+        case(
+            "unknown opcodes fail",
+            0x5121,
+            machine(),
+            expect().error(Chip8Error::UnknownOpcode {
+                pc: 0x200,
+                opcode: 0x5121,
+            }),
+        ),
+    ]);
+}
+
+#[test]
+fn skips() {
+    let v3 = || machine().with_register(3, 0x12);
+    let v1_v2 = |a, b| machine().with_registers(&[0, a, b]);
+    check(vec![
+        case("3XNN skips if equal", 0x3312, v3(), expect().pc(0x204)),
+        case("3XNN runs on if not", 0x3313, v3(), expect()),
+        case(
+            "3XNN skips all of F000 NNNN",
+            0x3312,
+            v3().with_memory(0x202, &[0xF0, 0x00, 0x12, 0x34]),
+            expect().pc(0x206),
+        ),
+        case("4XNN runs on if equal", 0x4312, v3(), expect()),
+        case("4XNN skips if not", 0x4313, v3(), expect().pc(0x204)),
+        case(
+            "5XY0 skips if equal",
+            0x5120,
+            v1_v2(7, 7),
+            expect().pc(0x204),
+        ),
+        case("5XY0 runs on if not", 0x5120, v1_v2(7, 8), expect()),
+        case("9XY0 runs on if equal", 0x9120, v1_v2(7, 7), expect()),
+        case("9XY0 skips if not", 0x9120, v1_v2(7, 8), expect().pc(0x204)),
+        case(
+            "EX9E skips if held",
+            0xE49E,
+            machine().with_register(4, 5).with_keys(1 << 5),
+            expect().pc(0x204),
+        ),
+        case(
+            "EX9E runs on if another key is held",
+            0xE49E,
+            machine().with_register(4, 5).with_keys(1 << 6),
+            expect(),
+        ),
+        case(
+            "EXA1 runs on if held",
+            0xE4A1,
+            machine().with_register(4, 5).with_keys(1 << 5),
+            expect(),
+        ),
+        case(
+            "EXA1 skips if not held",
+            0xE4A1,
+            machine().with_register(4, 5),
+            expect().pc(0x204),
+        ),
+    ]);
+}
+
+#[test]
+fn registers() {
+    let v1_v2 = |a, b| machine().with_registers(&[0, a, b]);
+    // VF starts at 5 to show whether the logic ops reset it
+    let logic = |quirks| {
+        machine()
+            .with_quirks(quirks)
+            .with_registers(&[0, 0b1100, 0b1010])
+            .with_register(0xF, 5)
+    };
+    check(vec![
+        case("6XNN sets", 0x6A42, machine(), expect().v(0xA, 0x42)),
+        case(
+            "7XNN wraps without touching VF",
+            0x7A02,
+            machine().with_register(0xA, 0xFF).with_register(0xF, 5),
+            expect().v(0xA, 0x01),
+        ),
+        case("8XY0 copies", 0x8120, v1_v2(1, 2), expect().v(1, 2)),
+        case(
+            "8XY1 resets VF on the VIP",
+            0x8121,
+            logic(vip()),
+            expect().v(1, 0b1110).vf(0),
+        ),
+        case(
+            "8XY1 leaves VF on SUPER-CHIP",
+            0x8121,
+            logic(schip()),
+            expect().v(1, 0b1110),
+        ),
+        case(
+            "8XY2 resets VF on the VIP",
+            0x8122,
+            logic(vip()),
+            expect().v(1, 0b1000).vf(0),
+        ),
+        case(
+            "8XY2 leaves VF on SUPER-CHIP",
+            0x8122,
+            logic(schip()),
+            expect().v(1, 0b1000),
+        ),
+        case(
+            "8XY3 resets VF on the VIP",
+            0x8123,
+            logic(vip()),
+            expect().v(1, 0b0110).vf(0),
+        ),
+        case(
+            "8XY3 leaves VF on SUPER-CHIP",
+            0x8123,
+            logic(schip()),
+            expect().v(1, 0b0110),
+        ),
+        case(
+            "8XY1 reads VF before resetting it",
+            0x81F1,
+            logic(vip()),
+            expect().v(1, 0b1101).vf(0),
+        ),
+        case(
+            "8XY4 carries",
+            0x8124,
+            v1_v2(0xF0, 0x20),
+            expect().v(1, 0x10).vf(1),
+        ),
+        case(
+            "8XY4 without carry",
+            0x8124,
+            v1_v2(0x10, 0x20),
+            expect().v(1, 0x30).vf(0),
+        ),
+        case(
+            "8XY4 into VF keeps the carry",
+            0x8F24,
+            v1_v2(0, 0x20).with_register(0xF, 0xF0),
+            expect().vf(1),
+        ),
+        case(
+            "8XY5 without borrow",
+            0x8125,
+            v1_v2(5, 3),
+            expect().v(1, 2).vf(1),
+        ),
+        case(
+            "8XY5 of equal values doesn't borrow",
+            0x8125,
+            v1_v2(5, 5),
+            expect().v(1, 0).vf(1),
+        ),
+        case(
+            "8XY5 borrows",
+            0x8125,
+            v1_v2(3, 5),
+            expect().v(1, 0xFE).vf(0),
+        ),
+        case(
+            "8XY7 without borrow",
+            0x8127,
+            v1_v2(3, 5),
+            expect().v(1, 2).vf(1),
+        ),
+        case(
+            "8XY7 borrows",
+            0x8127,
+            v1_v2(5, 3),
+            expect().v(1, 0xFE).vf(0),
+        ),
+        case(
+            "8XY6 shifts VY on the VIP",
+            0x8126,
+            v1_v2(0, 0b101).with_quirks(vip()),
+            expect().v(1, 0b10).vf(1),
+        ),
+        case(
+            "8XY6 shifts VX on SUPER-CHIP",
+            0x8126,
+            v1_v2(0b100, 0xFF).with_quirks(schip()),
+            expect().v(1, 0b10).vf(0),
+        ),
+        case(
+            "8XYE shifts VY on the VIP",
+            0x812E,
+            v1_v2(0, 0x81).with_quirks(vip()),
+            expect().v(1, 0x02).vf(1),
+        ),
+        case(
+            "8XYE shifts VX on SUPER-CHIP",
+            0x812E,
+            v1_v2(0x40, 0xFF).with_quirks(schip()),
+            expect().v(1, 0x80).vf(0),
+        ),
+        case(
+            "CXNN masks the random number",
+            0xC500,
+            machine().with_register(5, 0xFF),
+            expect().v(5, 0),
+        ),
+    ]);
+}
+
+#[test]
+fn index_and_memory() {
+    let three = || machine().with_registers(&[1, 2, 3]).with_i(0x300);
+    let stored = || machine().with_memory(0x300, &[7, 8, 9]).with_i(0x300);
+    check(vec![
+        case("ANNN sets I", 0xA123, machine(), expect().i(0x123)),
+        case(
+            "F000 NNNN loads a 16 bit I",
+            0xF000,
+            machine().with_memory(0x202, &[0x12, 0x34]),
+            expect().i(0x1234).pc(0x204),
+        ),
+        case(
+            "FX1E wraps I without touching VF",
+            0xF11E,
+            machine().with_i(0xFFFF).with_register(1, 2),
+            expect().i(1),
+        ),
+        case(
+            "FX29 points at the low nibble's digit",
+            0xF229,
+            machine().with_register(2, 0x1A),
+            expect().i(0x50 + 5 * 0xA),
+        ),
+        case(
+            "FX30 points at a big digit",
+            0xF230,
+            machine().with_register(2, 3),
+            expect().i(0xA0 + 10 * 3),
+        ),
+        case(
+            "FX33 stores decimal digits",
+            0xF233,
+            machine().with_register(2, 156).with_i(0x300),
+            expect().memory(0x300, &[1, 5, 6]),
+        ),
+        case(
+            "FX33 past the end of memory",
+            0xF233,
+            machine().with_i(0xFFFE),
+            expect().error(Chip8Error::MemoryOutOfBounds {
+                pc: 0x200,
+                addr: 0xFFFE,
+            }),
+        ),
+        case(
+            "FX55 increments I on the VIP",
+            0xF255,
+            three().with_quirks(vip()),
+            expect().memory(0x300, &[1, 2, 3, 0]).i(0x303),
+        ),
+        case(
+            "FX55 leaves I on SUPER-CHIP",
+            0xF255,
+            three().with_quirks(schip()),
+            expect().memory(0x300, &[1, 2, 3, 0]),
+        ),
+        case(
+            "FX65 increments I on the VIP",
+            0xF165,
+            stored().with_quirks(vip()),
+            expect().v(0, 7).v(1, 8).i(0x302),
+        ),
+        case(
+            "FX65 leaves I on SUPER-CHIP",
+            0xF165,
+            stored().with_quirks(schip()),
+            expect().v(0, 7).v(1, 8),
+        ),
+        case(
+            "FX55 past the end of memory",
+            0xF255,
+            machine().with_i(0xFFFE),
+            expect().error(Chip8Error::MemoryOutOfBounds {
+                pc: 0x200,
+                addr: 0xFFFE,
+            }),
+        ),
+        case(
+            "5XY2 stores a range",
+            0x5132,
+            three(),
+            expect().memory(0x300, &[2, 3, 0]),
+        ),
+        case(
+            "5XY2 stores a range backwards",
+            0x5212,
+            three(),
+            expect().memory(0x300, &[3, 2, 0]),
+        ),
+        case(
+            "5XY3 loads a range",
+            0x5133,
+            stored(),
+            expect().v(1, 7).v(2, 8).v(3, 9),
+        ),
+        case(
+            "5XY3 loads a range backwards",
+            0x5313,
+            stored(),
+            expect().v(3, 7).v(2, 8).v(1, 9),
+        ),
+        case(
+            "5XY2 past the end of memory",
+            0x5012,
+            machine().with_i(0xFFFF),
+            expect().error(Chip8Error::MemoryOutOfBounds {
+                pc: 0x200,
+                addr: 0xFFFF,
+            }),
+        ),
+    ]);
+}
+
+#[test]
+fn timers_and_sound() {
+    let pattern = [0xAA; 16];
+    check(vec![
+        case(
+            "FX07 reads the delay timer",
+            0xF307,
+            machine().with_timers(7, 0),
+            expect().v(3, 7),
+        ),
+        case(
+            "FX15 sets the delay timer",
+            0xF315,
+            machine().with_register(3, 9),
+            expect().timers(9, 0),
+        ),
+        case(
+            "FX18 starts the tone",
+            0xF318,
+            machine().with_register(3, 9),
+            expect().timers(0, 9).audio(&[AudioEvent::StartTone]),
+        ),
+        case(
+            "FX18 keeps a playing tone going",
+            0xF318,
+            machine().with_register(3, 9).with_timers(0, 2),
+            expect().timers(0, 9),
+        ),
+        case(
+            "FX18 with 0 stops the tone",
+            0xF318,
+            machine().with_timers(0, 3),
+            expect().timers(0, 0).audio(&[AudioEvent::StopTone]),
+        ),
+        case(
+            "F002 loads the audio pattern",
+            0xF002,
+            machine().with_memory(0x300, &pattern).with_i(0x300),
+            expect().audio(&[AudioEvent::Pattern(pattern)]),
+        ),
+        case(
+            "FX3A sets the pitch",
+            0xF23A,
+            machine().with_register(2, 0x70),
+            expect().audio(&[AudioEvent::Pitch(0x70)]),
+        ),
+    ]);
+}
+
+#[test]
+fn display() {
+    // a 2 pixel wide sprite at 0x300, and one for each XO-CHIP plane at 0x310
+    let sprite = |quirks| {
+        machine()
+            .with_quirks(quirks)
+            .with_vblank()
+            .with_memory(0x300, &[0xC0, 0xC0])
+            .with_memory(0x310, &[0x80, 0x40])
+            .with_i(0x300)
+    };
+    let at = |quirks, x, y| sprite(quirks).with_registers(&[x, y]);
+    let mut big = [0; 32];
+    big[..2].copy_from_slice(&[0x80, 0x01]);
+    check(vec![
+        case(
+            "00E0 clears",
+            0x00E0,
+            machine().with_pixels(1, &[(0, 0), (63, 31)]),
+            expect().pixels(&[]),
+        ),
+        case(
+            "00E0 clears only the selected planes",
+            0x00E0,
+            machine()
+                .with_pixels(0b11, &[(1, 1)])
+                .with_selected_planes(0b10),
+            expect().plane_pixels(&[(1, 1, 0b01)]),
+        ),
+        case(
+            "00CN scrolls down",
+            0x00C2,
+            machine().with_pixels(1, &[(5, 0), (5, 31)]),
+            expect().pixels(&[(5, 2)]),
+        ),
+        case(
+            "00DN scrolls up",
+            0x00D2,
+            machine().with_pixels(1, &[(5, 3), (5, 0)]),
+            expect().pixels(&[(5, 1)]),
+        ),
+        case(
+            "00FB scrolls right",
+            0x00FB,
+            machine().with_pixels(1, &[(0, 0), (62, 0)]),
+            expect().pixels(&[(4, 0)]),
+        ),
+        case(
+            "00FC scrolls left",
+            0x00FC,
+            machine().with_pixels(1, &[(4, 0), (1, 0)]),
+            expect().pixels(&[(0, 0)]),
+        ),
+        case(
+            "00FF switches to hires and clears",
+            0x00FF,
+            machine().with_pixels(1, &[(0, 0)]),
+            expect().hires(true).pixels(&[]),
+        ),
+        case(
+            "00FE switches to lores and clears",
+            0x00FE,
+            machine().with_hires().with_pixels(1, &[(100, 50)]),
+            expect().hires(false).pixels(&[]),
+        ),
+        case(
+            "FN01 selects planes",
+            0xF201,
+            machine(),
+            expect().selected_planes(0b10),
+        ),
+        case(
+            "DXYN draws",
+            0xD011,
+            at(vip(), 1, 2),
+            expect().pixels(&[(1, 2), (2, 2)]).vf(0),
+        ),
+        case(
+            "DXYN flags a collision",
+            0xD011,
+            at(vip(), 1, 2).with_pixels(1, &[(1, 2)]),
+            expect().pixels(&[(2, 2)]).vf(1),
+        ),
+        case(
+            "DXYN waits for the vertical blank on the VIP",
+            0xD011,
+            machine()
+                .with_quirks(vip())
+                .with_memory(0x300, &[0x80])
+                .with_i(0x300)
+                .with_register(0xF, 5),
+            expect().pc(0x200),
+        ),
+        case(
+            "DXYN doesn't wait on SUPER-CHIP",
+            0xD011,
+            machine()
+                .with_quirks(schip())
+                .with_memory(0x300, &[0x80])
+                .with_i(0x300),
+            expect().pixels(&[(0, 0)]).vf(0),
+        ),
+        case(
+            "DXYN wraps the starting position",
+            0xD011,
+            at(vip(), 65, 34),
+            expect().pixels(&[(1, 2), (2, 2)]).vf(0),
+        ),
+        case(
+            "DXYN clips at the right edge on the VIP",
+            0xD011,
+            at(vip(), 63, 2),
+            expect().pixels(&[(63, 2)]).vf(0),
+        ),
+        case(
+            "DXYN wraps at the right edge on XO-CHIP",
+            0xD011,
+            at(xochip(), 63, 2),
+            expect().pixels(&[(63, 2), (0, 2)]).vf(0),
+        ),
+        case(
+            "DXYN clips at the bottom on the VIP",
+            0xD012,
+            at(vip(), 0, 31),
+            expect().pixels(&[(0, 31), (1, 31)]).vf(0),
+        ),
+        case(
+            "DXYN wraps at the bottom on XO-CHIP",
+            0xD012,
+            at(xochip(), 0, 31),
+            expect().pixels(&[(0, 31), (1, 31), (0, 0), (1, 0)]).vf(0),
+        ),
+        case(
+            "DXY0 draws 16x16 in hires",
+            0xD010,
+            machine()
+                .with_quirks(schip())
+                .with_hires()
+                .with_memory(0x300, &big)
+                .with_i(0x300),
+            expect().pixels(&[(0, 0), (15, 0)]).vf(0),
+        ),
+        case(
+            "DXYN draws a sprite per selected plane",
+            0xD011,
+            sprite(xochip()).with_i(0x310).with_selected_planes(0b11),
+            expect().plane_pixels(&[(0, 0, 0b01), (1, 0, 0b10)]).vf(0),
+        ),
+        case(
+            "DXYN past the end of memory",
+            0xD012,
+            machine().with_quirks(schip()).with_i(0xFFFF),
+            expect().vf(0).error(Chip8Error::MemoryOutOfBounds {
+                pc: 0x200,
+                addr: 0xFFFF,
+            }),
+        ),
+    ]);
+}
+
+#[test]
+fn random_stays_within_mask() {
+    let mut em = machine().build();
+    for _ in 0..100 {
+        em.pc = 0x200;
+        em.execute(0xC50F).unwrap();
+        assert_eq!(em.v[5] & 0xF0, 0);
+    }
+}
+
+#[test]
+fn display_wait_allows_one_draw_per_frame() {
+    let mut em = machine()
+        .with_quirks(vip())
+        .with_vblank()
+        .with_memory(0x300, &[0x80])
+        .with_i(0x300)
+        .build();
+    em.execute(0xD011).unwrap();
+    assert_eq!(em.pc, 0x202);
+
+    em.execute(0xD011).unwrap();
+    assert_eq!(em.pc, 0x202, "second draw should wait");
+    assert!(em.is_waiting_for_vblank());
+
+    em.decrement_timers();
+    em.execute(0xD011).unwrap();
+    assert_eq!(em.pc, 0x204);
+}
+
+#[test]
+fn fx0a_waits_for_press_and_release() {
+    let mut em = machine().build();
+    em.execute(0xF30A).unwrap();
+    assert_eq!(em.pc, 0x200, "should wait with no key held");
+
+    em.set_key(7, true);
+    em.decrement_timers();
+    em.execute(0xF30A).unwrap();
+    assert_eq!(em.pc, 0x200, "should wait for the key to be released");
+
+    em.set_key(7, false);
+    em.decrement_timers();
+    em.execute(0xF30A).unwrap();
+    assert_eq!(em.pc, 0x202);
+    assert_eq!(em.v[3], 7);
+}
+
+#[test]
+fn fx0a_ignores_keys_held_before_it_ran() {
+    let mut em = machine().build();
+    em.set_key(7, true);
+    em.decrement_timers();
+    em.decrement_timers();
+    em.execute(0xF30A).unwrap();
+    em.set_key(7, false);
+    em.decrement_timers();
+    em.execute(0xF30A).unwrap();
+    assert_eq!(em.pc, 0x200);
+}
+
+#[test]
+fn fx75_and_fx85_keep_flags() {
+    let mut em = machine().with_registers(&[1, 2, 3]).build();
+    em.execute(0xF175).unwrap();
+    em.v = [0; 16];
+    em.execute(0xF285).unwrap();
+    assert_eq!(em.v[..3], [1, 2, 0]);
+}
+
+#[test]
+fn log_policy_keeps_errors_for_the_frontend() {
+    // 00EE with an empty stack, twice
+    let mut em = machine()
+        .with_memory(0x200, &[0x00, 0xEE, 0x00, 0xEE])
+        .build();
+    em.error_policy = ErrorPolicy::Log;
+    em.step().unwrap();
+    em.step().unwrap();
+    assert_eq!(em.pc, 0x204);
+    assert!(!em.has_halted());
+
+    let errors = em.take_logged_errors();
+    assert!(matches!(
+        errors[..],
+        [
+            Chip8Error::StackUnderflow { pc: 0x200 },
+            Chip8Error::StackUnderflow { pc: 0x202 }
+        ]
+    ));
+    assert!(em.take_logged_errors().is_empty());
+}
+
+#[test]
+fn only_taken_skips_cost_extra_cycles() {
+    // 3000 skips the instruction after it, 3001 doesn't
+    let mut em = machine()
+        .with_memory(0x200, &[0x30, 0x00, 0x00, 0x00, 0x30, 0x01])
+        .build();
+    em.step().unwrap();
+    assert_eq!(em.pc, 0x204);
+    let taken = em.last_cycles();
+    em.step().unwrap();
+    assert_eq!(em.pc, 0x206);
+    assert_eq!(taken - em.last_cycles(), 4);
+}
+
+#[test]
+fn memory_mapped_stack_stays_in_the_vip_stack_area() {
+    let quirks = Quirks {
+        memory_mapped_stack: true,
+        stack_depth: 40000,
+        ..vip()
+    };
+    let mut em = machine()
+        .with_quirks(quirks)
+        .with_stack(&[0x300; 24])
+        .build();
+    assert!(matches!(
+        em.execute(0x2ABC),
+        Err(Chip8Error::StackOverflow { pc: 0x200 })
+    ));
+    assert_eq!(em.memory[0xED0..0xED2], [0, 0]);
+
+    // only a stack set up from outside can get this deep
+    let mut em = machine()
+        .with_quirks(quirks)
+        .with_stack(&[0x300; 40000])
+        .build();
+    assert!(matches!(
+        em.execute(0x00EE),
+        Err(Chip8Error::MemoryOutOfBounds { pc: 0x200, .. })
+    ));
+}
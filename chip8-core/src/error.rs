@@ -0,0 +1,92 @@
+use std::{fmt, io, str::FromStr};
+
+/// Something a rom did that the interpreter can't carry out.
+#[derive(Debug)]
+pub enum Chip8Error {
+    /// The rom file couldn't be read.
+    Io(io::Error),
+    /// The rom doesn't fit in memory after 0x200.
+    RomTooLarge {
+        size: usize,
+        max: usize,
+    },
+    /// 00EE with nothing on the stack.
+    StackUnderflow {
+        pc: usize,
+    },
+    /// 2NNN with the stack already full.
+    StackOverflow {
+        pc: usize,
+    },
+    /// PC ran off the end of memory.
+    PcOutOfBounds {
+        pc: usize,
+    },
+    /// An instruction read or wrote past the end of memory, starting at ```addr```.
+    MemoryOutOfBounds {
+        pc: usize,
+        addr: usize,
+    },
+    UnknownOpcode {
+        pc: usize,
+        opcode: u16,
+    },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::Io(e) => write!(f, "Couldn't read rom: {e}"),
+            Chip8Error::RomTooLarge { size, max } => {
+                write!(f, "Rom is {size} bytes, the most that fits is {max}")
+            }
+            Chip8Error::StackUnderflow { pc } => {
+                write!(f, "{pc:#05X}: returned with an empty stack")
+            }
+            Chip8Error::StackOverflow { pc } => write!(f, "{pc:#05X}: call stack overflowed"),
+            Chip8Error::PcOutOfBounds { pc } => write!(f, "{pc:#05X}: pc is outside memory"),
+            Chip8Error::MemoryOutOfBounds { pc, addr } => {
+                write!(f, "{pc:#05X}: accessed memory past the end at {addr:#06X}")
+            }
+            Chip8Error::UnknownOpcode { pc, opcode } => {
+                write!(f, "{pc:#05X}: unknown opcode {opcode:04X}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+impl From<io::Error> for Chip8Error {
+    fn from(e: io::Error) -> Self {
+        Chip8Error::Io(e)
+    }
+}
+
+/// What ```Chip8::step``` does when an instruction fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    /// Stop running and return the error. Later steps do nothing.
+    #[default]
+    Halt,
+    /// Skip the instruction and carry on, keeping the error for the frontend to
+    /// print. See ```Chip8::take_logged_errors```.
+    Log,
+    /// Skip the instruction and carry on.
+    Ignore,
+}
+
+impl FromStr for ErrorPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "halt" => Ok(ErrorPolicy::Halt),
+            "log" => Ok(ErrorPolicy::Log),
+            "ignore" => Ok(ErrorPolicy::Ignore),
+            _ => Err(format!(
+                "Unknown error policy {s}, expected halt, log or ignore"
+            )),
+        }
+    }
+}
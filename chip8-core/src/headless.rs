@@ -0,0 +1,188 @@
+use std::{fmt, io, path::Path, str::FromStr};
+
+use crate::{
+    emulator::Chip8,
+    error::Chip8Error,
+    scheduler::{Scheduler, Timing},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Holds a Chip-8 key down over a range of frames, e.g. ```5@10``` or ```A@30-45```.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub key: u8,
+    pub first_frame: usize,
+    /// Inclusive.
+    pub last_frame: usize,
+}
+
+impl KeyPress {
+    fn is_down(&self, frame: usize) -> bool {
+        (self.first_frame..=self.last_frame).contains(&frame)
+    }
+}
+
+impl FromStr for KeyPress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid key press {s:?}, expected <key>@<frame>[-<frame>]");
+
+        let (key, frames) = s.split_once('@').ok_or_else(invalid)?;
+        let key = u8::from_str_radix(key, 16)
+            .ok()
+            .filter(|k| *k < 16)
+            .ok_or_else(invalid)?;
+        let (first, last) = frames.split_once('-').unwrap_or((frames, frames));
+        let first_frame = first.parse().map_err(|_| invalid())?;
+        let last_frame = last.parse().map_err(|_| invalid())?;
+
+        Ok(Self {
+            key,
+            first_frame,
+            last_frame,
+        })
+    }
+}
+
+/// Why a headless run stopped.
+#[derive(Debug)]
+pub enum StopReason {
+    /// Ran every frame it was asked to.
+    FrameLimit,
+    /// PC reached the address it was told to stop at.
+    ReachedPc,
+    /// The program exited with 00FD or is stuck jumping to itself,
+    /// which is how most test roms finish.
+    Halted,
+    /// An instruction failed and the error policy is to halt.
+    Error(Chip8Error),
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::FrameLimit => f.write_str("frame limit reached"),
+            StopReason::ReachedPc => f.write_str("pc reached"),
+            StopReason::Halted => f.write_str("program halted"),
+            StopReason::Error(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Runs the emulator without a window or audio, e.g. on CI.
+/// A frame is one 60Hz timer tick, so runs don't depend on how fast the machine is.
+#[derive(Debug, Clone, Default)]
+pub struct Headless {
+    pub frames: usize,
+    pub timing: Timing,
+    /// Stop as soon as PC reaches this address.
+    pub until_pc: Option<usize>,
+    pub presses: Vec<KeyPress>,
+}
+
+impl Headless {
+    /// Runs until a stop condition is hit.
+    /// Returns the number of frames run and why it stopped.
+    pub fn run(&self, em: &mut Chip8) -> (usize, StopReason) {
+        let mut scheduler = Scheduler::new(self.timing);
+        for frame in 0..self.frames {
+            let keys = self
+                .presses
+                .iter()
+                .filter(|p| p.is_down(frame))
+                .fold(0, |keys, p| keys | 1 << p.key);
+
+            let stopped = scheduler.tick_until(em, keys, |em| {
+                self.until_pc == Some(em.pc()) || is_halted(em)
+            });
+            match stopped {
+                Err(e) => return (frame + 1, StopReason::Error(e)),
+                Ok(true) if self.until_pc == Some(em.pc()) => {
+                    return (frame + 1, StopReason::ReachedPc);
+                }
+                Ok(true) => return (frame + 1, StopReason::Halted),
+                Ok(false) => {}
+            }
+        }
+        (self.frames, StopReason::FrameLimit)
+    }
+}
+
+/// Whether the program exited or is about to jump to itself forever.
+pub fn is_halted(em: &Chip8) -> bool {
+    let opcode = em.peek_opcode();
+    // 1NNN only reaches the first 4K, so past that it can't be jumping to itself
+    em.has_exited()
+        || em.pc() <= 0xFFF && opcode & 0xF000 == 0x1000 && (opcode & 0x0FFF) as usize == em.pc()
+}
+
+/// Formats the display can be dumped in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Full color, from the palette.
+    Png,
+    /// Plain (P1) black and white bitmap. Any pixel that isn't the background is set.
+    Pbm,
+    /// ```#``` for set pixels and ```.``` for background, one line per row.
+    Ascii,
+}
+
+impl ImageFormat {
+    /// Picks the format from a file extension, defaulting to ASCII.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("png") => ImageFormat::Png,
+            Some(ext) if ext.eq_ignore_ascii_case("pbm") => ImageFormat::Pbm,
+            _ => ImageFormat::Ascii,
+        }
+    }
+}
+
+/// Encodes the current display.
+pub fn encode_display(em: &Chip8, format: ImageFormat) -> io::Result<Vec<u8>> {
+    let (width, height) = (em.width(), em.height());
+    let background = em.palette[0];
+    let rows = em.framebuffer().chunks(width);
+
+    let mut out = Vec::new();
+    match format {
+        ImageFormat::Png => {
+            let mut encoder = png::Encoder::new(&mut out, width as u32, height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let pixels: Vec<u8> = em
+                .framebuffer()
+                .iter()
+                .flat_map(|color| [(color >> 16) as u8, (color >> 8) as u8, *color as u8])
+                .collect();
+            encoder
+                .write_header()
+                .and_then(|mut writer| writer.write_image_data(&pixels))
+                .map_err(io::Error::other)?;
+        }
+        ImageFormat::Pbm => {
+            out.extend(format!("P1\n{width} {height}\n").as_bytes());
+            for row in rows {
+                let bits: Vec<&str> = row
+                    .iter()
+                    .map(|color| if *color == background { "0" } else { "1" })
+                    .collect();
+                out.extend(bits.join(" ").as_bytes());
+                out.push(b'\n');
+            }
+        }
+        ImageFormat::Ascii => {
+            for row in rows {
+                out.extend(
+                    row.iter()
+                        .map(|color| if *color == background { b'.' } else { b'#' }),
+                );
+                out.push(b'\n');
+            }
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,65 @@
+use super::{Headless, StopReason};
+use crate::{emulator::Chip8, scheduler::Timing};
+
+fn run(rom: &[u8], headless: Headless) -> (usize, StopReason) {
+    let mut em = Box::new(Chip8::new());
+    em.load_rom_bytes(rom).unwrap();
+    headless.run(&mut em)
+}
+
+fn frames(frames: usize) -> Headless {
+    Headless {
+        frames,
+        ..Headless::default()
+    }
+}
+
+#[test]
+fn stops_on_a_jump_to_itself() {
+    // V0 = 1, then 1202 forever
+    let (frames, reason) = run(&[0x60, 0x01, 0x12, 0x02], frames(60));
+    assert_eq!(frames, 1);
+    assert!(matches!(reason, StopReason::Halted));
+}
+
+#[test]
+fn stops_on_exit() {
+    let (frames, reason) = run(&[0x60, 0x01, 0x00, 0xFD], frames(60));
+    assert_eq!(frames, 1);
+    assert!(matches!(reason, StopReason::Halted));
+}
+
+#[test]
+fn runs_every_frame_of_a_loop() {
+    // 1200 jumps back to the start, which isn't a jump to itself
+    let (frames, reason) = run(&[0x60, 0x01, 0x12, 0x00], frames(60));
+    assert_eq!(frames, 60);
+    assert!(matches!(reason, StopReason::FrameLimit));
+}
+
+#[test]
+fn jumps_past_4k_arent_jumps_to_themselves() {
+    // 8000 up to 0x1200, where 1200 jumps back to the start. 0x1200 only
+    // looks like a jump to itself if its address is cut down to 12 bits.
+    let mut rom = [0x80, 0x00].repeat(0x800);
+    rom.extend([0x12, 0x00]);
+    let headless = Headless {
+        frames: 2,
+        timing: Timing::InstructionsPerSecond(600_000),
+        ..Headless::default()
+    };
+    let (frames, reason) = run(&rom, headless);
+    assert_eq!(frames, 2);
+    assert!(matches!(reason, StopReason::FrameLimit), "{reason}");
+}
+
+#[test]
+fn stops_at_the_pc_asked_for() {
+    let headless = Headless {
+        until_pc: Some(0x202),
+        ..frames(60)
+    };
+    let (frames, reason) = run(&[0x60, 0x01, 0x12, 0x00], headless);
+    assert_eq!(frames, 1);
+    assert!(matches!(reason, StopReason::ReachedPc));
+}
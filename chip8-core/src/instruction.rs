@@ -0,0 +1,327 @@
+/// A decoded opcode. Shared by the interpreter and the disassembler
+/// so both agree on what every opcode means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// 00E0
+    Clear,
+    /// 00EE
+    Return,
+    /// 00CN
+    ScrollDown {
+        n: usize,
+    },
+    /// 00DN
+    ScrollUp {
+        n: usize,
+    },
+    /// 00FB
+    ScrollRight,
+    /// 00FC
+    ScrollLeft,
+    /// 00FD
+    Exit,
+    /// 00FE
+    Lores,
+    /// 00FF
+    Hires,
+    /// 0NNN, machine code routine on the original hardware
+    Sys {
+        nnn: u16,
+    },
+    /// 1NNN
+    Jump {
+        nnn: u16,
+    },
+    /// 2NNN
+    Call {
+        nnn: u16,
+    },
+    /// 3XNN
+    SkipIfEqual {
+        x: usize,
+        nn: u8,
+    },
+    /// 4XNN
+    SkipIfNotEqual {
+        x: usize,
+        nn: u8,
+    },
+    /// 5XY0
+    SkipIfRegistersEqual {
+        x: usize,
+        y: usize,
+    },
+    /// 5XY2
+    SaveRange {
+        x: usize,
+        y: usize,
+    },
+    /// 5XY3
+    LoadRange {
+        x: usize,
+        y: usize,
+    },
+    /// 6XNN
+    Set {
+        x: usize,
+        nn: u8,
+    },
+    /// 7XNN
+    Add {
+        x: usize,
+        nn: u8,
+    },
+    /// 8XY0
+    Copy {
+        x: usize,
+        y: usize,
+    },
+    /// 8XY1
+    Or {
+        x: usize,
+        y: usize,
+    },
+    /// 8XY2
+    And {
+        x: usize,
+        y: usize,
+    },
+    /// 8XY3
+    Xor {
+        x: usize,
+        y: usize,
+    },
+    /// 8XY4
+    AddRegisters {
+        x: usize,
+        y: usize,
+    },
+    /// 8XY5
+    Subtract {
+        x: usize,
+        y: usize,
+    },
+    /// 8XY6
+    ShiftRight {
+        x: usize,
+        y: usize,
+    },
+    /// 8XY7
+    SubtractReversed {
+        x: usize,
+        y: usize,
+    },
+    /// 8XYE
+    ShiftLeft {
+        x: usize,
+        y: usize,
+    },
+    /// 9XY0
+    SkipIfRegistersNotEqual {
+        x: usize,
+        y: usize,
+    },
+    /// ANNN
+    SetIndex {
+        nnn: u16,
+    },
+    /// BNNN, BXNN with the jump quirk
+    JumpWithOffset {
+        x: usize,
+        nnn: u16,
+    },
+    /// CXNN
+    Random {
+        x: usize,
+        nn: u8,
+    },
+    /// DXYN
+    Draw {
+        x: usize,
+        y: usize,
+        n: usize,
+    },
+    /// EX9E
+    SkipIfKey {
+        x: usize,
+    },
+    /// EXA1
+    SkipIfNotKey {
+        x: usize,
+    },
+    /// F000 NNNN, the address is in the next 2 bytes
+    LoadLongIndex,
+    /// FN01
+    SelectPlanes {
+        n: usize,
+    },
+    /// F002
+    LoadAudioPattern,
+    /// FX07
+    GetDelayTimer {
+        x: usize,
+    },
+    /// FX0A
+    WaitForKey {
+        x: usize,
+    },
+    /// FX15
+    SetDelayTimer {
+        x: usize,
+    },
+    /// FX18
+    SetSoundTimer {
+        x: usize,
+    },
+    /// FX1E
+    AddToIndex {
+        x: usize,
+    },
+    /// FX29
+    Font {
+        x: usize,
+    },
+    /// FX30
+    BigFont {
+        x: usize,
+    },
+    /// FX33
+    Bcd {
+        x: usize,
+    },
+    /// FX3A
+    SetPitch {
+        x: usize,
+    },
+    /// FX55
+    Store {
+        x: usize,
+    },
+    /// FX65
+    Load {
+        x: usize,
+    },
+    /// FX75
+    SaveFlags {
+        x: usize,
+    },
+    /// FX85
+    LoadFlags {
+        x: usize,
+    },
+    Unknown {
+        opcode: u16,
+    },
+}
+
+impl Instruction {
+    pub fn decode(opcode: u16) -> Self {
+        let high_nibble = (opcode & 0xF000) >> 12;
+        // second nibble - used for lookup into V (vx)
+        let x = ((opcode & 0x0F00) >> 8) as usize;
+        // third nibble
+        let y = ((opcode & 0x00F0) >> 4) as usize;
+        // fourth nibble
+        let n = (opcode & 0xF) as usize;
+        // 3rd & 4th nibbles
+        let nn = (opcode & 0x00FF) as u8;
+        // 2nd 3rd & 4th nibbles
+        let nnn = opcode & 0x0FFF;
+
+        match high_nibble {
+            0x0 => match opcode {
+                0x00E0 => Self::Clear,
+                0x00EE => Self::Return,
+                0x00FB => Self::ScrollRight,
+                0x00FC => Self::ScrollLeft,
+                0x00FD => Self::Exit,
+                0x00FE => Self::Lores,
+                0x00FF => Self::Hires,
+                _ if opcode & 0xFFF0 == 0x00C0 => Self::ScrollDown { n },
+                _ if opcode & 0xFFF0 == 0x00D0 => Self::ScrollUp { n },
+                _ => Self::Sys { nnn },
+            },
+            0x1 => Self::Jump { nnn },
+            0x2 => Self::Call { nnn },
+            0x3 => Self::SkipIfEqual { x, nn },
+            0x4 => Self::SkipIfNotEqual { x, nn },
+            0x5 => match n {
+                0x0 => Self::SkipIfRegistersEqual { x, y },
+                0x2 => Self::SaveRange { x, y },
+                0x3 => Self::LoadRange { x, y },
+                _ => Self::Unknown { opcode },
+            },
+            0x6 => Self::Set { x, nn },
+            0x7 => Self::Add { x, nn },
+            0x8 => match n {
+                0x0 => Self::Copy { x, y },
+                0x1 => Self::Or { x, y },
+                0x2 => Self::And { x, y },
+                0x3 => Self::Xor { x, y },
+                0x4 => Self::AddRegisters { x, y },
+                0x5 => Self::Subtract { x, y },
+                0x6 => Self::ShiftRight { x, y },
+                0x7 => Self::SubtractReversed { x, y },
+                0xE => Self::ShiftLeft { x, y },
+                _ => Self::Unknown { opcode },
+            },
+            0x9 => match n {
+                0x0 => Self::SkipIfRegistersNotEqual { x, y },
+                _ => Self::Unknown { opcode },
+            },
+            0xA => Self::SetIndex { nnn },
+            0xB => Self::JumpWithOffset { x, nnn },
+            0xC => Self::Random { x, nn },
+            0xD => Self::Draw { x, y, n },
+            0xE => match nn {
+                0x9E => Self::SkipIfKey { x },
+                0xA1 => Self::SkipIfNotKey { x },
+                _ => Self::Unknown { opcode },
+            },
+            0xF => match nn {
+                0x00 if x == 0 => Self::LoadLongIndex,
+                0x01 => Self::SelectPlanes { n: x },
+                0x02 if x == 0 => Self::LoadAudioPattern,
+                0x07 => Self::GetDelayTimer { x },
+                0x0a => Self::WaitForKey { x },
+                0x15 => Self::SetDelayTimer { x },
+                0x18 => Self::SetSoundTimer { x },
+                0x1e => Self::AddToIndex { x },
+                0x29 => Self::Font { x },
+                0x30 => Self::BigFont { x },
+                0x33 => Self::Bcd { x },
+                0x3a => Self::SetPitch { x },
+                0x55 => Self::Store { x },
+                0x65 => Self::Load { x },
+                0x75 => Self::SaveFlags { x },
+                0x85 => Self::LoadFlags { x },
+                _ => Self::Unknown { opcode },
+            },
+            _ => {
+                // satisfies u16::MIN & u16::MAX
+                Self::Unknown { opcode }
+            }
+        }
+    }
+
+    /// Size in bytes, including the address following F000.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::LoadLongIndex => 4,
+            _ => 2,
+        }
+    }
+
+    /// Skips the instruction after it when a condition holds.
+    pub fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            Self::SkipIfEqual { .. }
+                | Self::SkipIfNotEqual { .. }
+                | Self::SkipIfRegistersEqual { .. }
+                | Self::SkipIfRegistersNotEqual { .. }
+                | Self::SkipIfKey { .. }
+                | Self::SkipIfNotKey { .. }
+        )
+    }
+}
@@ -0,0 +1,14 @@
+//! The Chip-8 interpreter, free of any window, input or audio library.
+//! Frontends feed it keys, run it a frame at a time and draw its framebuffer.
+
+pub mod audio;
+pub mod emulator;
+pub mod error;
+pub mod headless;
+pub mod instruction;
+pub mod quirks;
+pub mod rewind;
+pub mod romdb;
+pub mod savestate;
+pub mod scheduler;
+pub mod timing;
@@ -0,0 +1,120 @@
+use std::{fmt, str::FromStr};
+
+/// Behaviours that differ between CHIP-8 interpreters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8XY6 and 8XYE copy V```y``` into V```x``` before shifting.
+    pub shift_uses_vy: bool,
+    /// BNNN jumps to ```nnn``` plus V```x``` instead of V0.
+    pub jump_uses_vx: bool,
+    /// FX55 and FX65 leave I pointing past the last register touched.
+    pub load_store_increments_i: bool,
+    /// 8XY1, 8XY2 and 8XY3 set VF to 0.
+    pub vf_reset: bool,
+    /// Sprites are cut off at the screen edges instead of wrapping around.
+    pub clip_sprites: bool,
+    /// DXYN waits for the vertical blank interrupt before drawing.
+    pub display_wait: bool,
+    /// Nested calls allowed before 2NNN overflows the stack.
+    pub stack_depth: usize,
+    /// Keeps the call stack in memory at 0xEA0 like the COSMAC VIP did,
+    /// so programs can read and overwrite return addresses.
+    pub memory_mapped_stack: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Platform::default().quirks()
+    }
+}
+
+/// Interpreters with a known set of quirks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Platform {
+    #[default]
+    CosmacVip,
+    Chip48,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::CosmacVip => Quirks {
+                shift_uses_vy: true,
+                jump_uses_vx: false,
+                load_store_increments_i: true,
+                vf_reset: true,
+                clip_sprites: true,
+                display_wait: true,
+                stack_depth: 12,
+                memory_mapped_stack: false,
+            },
+            // CHIP-48 actually increments I by x rather than x + 1,
+            // which is closer to incrementing than leaving I alone.
+            Platform::Chip48 => Quirks {
+                shift_uses_vy: false,
+                jump_uses_vx: true,
+                load_store_increments_i: true,
+                vf_reset: false,
+                clip_sprites: true,
+                display_wait: false,
+                stack_depth: 16,
+                memory_mapped_stack: false,
+            },
+            Platform::SuperChip => Quirks {
+                shift_uses_vy: false,
+                jump_uses_vx: true,
+                load_store_increments_i: false,
+                vf_reset: false,
+                clip_sprites: true,
+                display_wait: false,
+                stack_depth: 16,
+                memory_mapped_stack: false,
+            },
+            Platform::XoChip => Quirks {
+                shift_uses_vy: true,
+                jump_uses_vx: false,
+                load_store_increments_i: true,
+                vf_reset: false,
+                clip_sprites: false,
+                display_wait: false,
+                stack_depth: 16,
+                memory_mapped_stack: false,
+            },
+        }
+    }
+
+    /// Name accepted on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::CosmacVip => "vip",
+            Platform::Chip48 => "chip48",
+            Platform::SuperChip => "schip",
+            Platform::XoChip => "xochip",
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "vip" | "cosmac-vip" | "chip8" | "chip-8" => Ok(Platform::CosmacVip),
+            "chip48" | "chip-48" => Ok(Platform::Chip48),
+            "schip" | "superchip" | "super-chip" => Ok(Platform::SuperChip),
+            "xochip" | "xo-chip" => Ok(Platform::XoChip),
+            _ => Err(format!(
+                "Unknown platform {s:?}. Expected one of: vip, chip48, schip, xochip"
+            )),
+        }
+    }
+}
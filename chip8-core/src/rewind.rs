@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+
+use crate::savestate::Snapshot;
+
+#[cfg(test)]
+mod tests;
+
+/// How to get from a frame back to the one before it.
+enum Delta {
+    /// Run length encoded XOR of the two frames' serialized snapshots.
+    Xor(Vec<u8>),
+    /// The whole earlier frame, for when the sizes differ, e.g. after switching to hires.
+    Full(Vec<u8>),
+}
+
+/// Ring buffer of the last few seconds of frames, for playing a game backwards.
+///
+/// Only the newest frame is kept whole. Every older frame is stored as the
+/// difference to the frame after it, which is tiny since most of memory
+/// doesn't change from one frame to the next.
+pub struct Rewind {
+    latest: Option<Vec<u8>>,
+    // oldest first
+    deltas: VecDeque<Delta>,
+    capacity: usize,
+}
+
+impl Rewind {
+    /// Keeps enough to step back ```capacity``` frames. A capacity of 0 records nothing.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            latest: None,
+            deltas: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records a frame, dropping the oldest one once the buffer is full.
+    pub fn push(&mut self, snapshot: &Snapshot) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let bytes = snapshot.to_bytes();
+        if let Some(previous) = self.latest.take() {
+            let delta = if previous.len() == bytes.len() {
+                Delta::Xor(encode_xor(&bytes, &previous))
+            } else {
+                Delta::Full(previous)
+            };
+            if self.deltas.len() == self.capacity {
+                self.deltas.pop_front();
+            }
+            self.deltas.push_back(delta);
+        }
+        self.latest = Some(bytes);
+    }
+
+    /// Steps back one frame and returns it.
+    /// Returns ```None``` once there are no earlier frames left.
+    pub fn pop(&mut self) -> Option<Snapshot> {
+        let delta = self.deltas.pop_back()?;
+        let latest = self.latest.as_mut()?;
+        match delta {
+            Delta::Xor(encoded) => apply_xor(latest, &encoded),
+            Delta::Full(bytes) => *latest = bytes,
+        }
+        // only ever holds bytes written by Snapshot::to_bytes
+        Snapshot::from_bytes(latest).ok()
+    }
+
+    /// Number of frames that can be stepped back through.
+    pub fn frames(&self) -> usize {
+        self.deltas.len()
+    }
+}
+
+/// Encodes ```a ^ b``` as alternating runs: a u16 count of zero bytes,
+/// a u16 count of literal bytes, then the literal bytes themselves.
+fn encode_xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut idx = 0;
+    while idx < a.len() {
+        let zeros_start = idx;
+        while idx < a.len() && a[idx] == b[idx] && idx - zeros_start < u16::MAX as usize {
+            idx += 1;
+        }
+        let literal_start = idx;
+        while idx < a.len() && a[idx] != b[idx] && idx - literal_start < u16::MAX as usize {
+            idx += 1;
+        }
+
+        out.extend(((literal_start - zeros_start) as u16).to_le_bytes());
+        out.extend(((idx - literal_start) as u16).to_le_bytes());
+        out.extend((literal_start..idx).map(|i| a[i] ^ b[i]));
+    }
+    out
+}
+
+/// XORs an ```encode_xor``` delta into ```bytes``` in place.
+fn apply_xor(bytes: &mut [u8], encoded: &[u8]) {
+    let mut idx = 0;
+    let mut runs = encoded;
+    while let [z0, z1, l0, l1, rest @ ..] = runs {
+        idx += u16::from_le_bytes([*z0, *z1]) as usize;
+        let literals = u16::from_le_bytes([*l0, *l1]) as usize;
+        for (byte, x) in bytes[idx..idx + literals].iter_mut().zip(rest) {
+            *byte ^= x;
+        }
+        idx += literals;
+        runs = &rest[literals..];
+    }
+}
@@ -0,0 +1,98 @@
+use super::{Delta, Rewind, apply_xor, encode_xor};
+use crate::{emulator::Chip8, savestate::Snapshot};
+
+/// Checks that the delta from ```b``` to ```a``` turns ```b``` back into ```a```.
+fn round_trip(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let encoded = encode_xor(a, b);
+    let mut bytes = b.to_vec();
+    apply_xor(&mut bytes, &encoded);
+    assert!(bytes == a, "applying the delta didn't give back the frame");
+    encoded
+}
+
+fn snapshot_with_pc(pc: usize) -> Snapshot {
+    let mut snapshot = Chip8::new().snapshot();
+    snapshot.pc = pc;
+    snapshot
+}
+
+#[test]
+fn xor_round_trips() {
+    let a: Vec<u8> = (0..1000).map(|i| (i * 7 % 251) as u8).collect();
+    let mut b = a.clone();
+    for i in (0..b.len()).step_by(3) {
+        b[i] ^= 0x5A;
+    }
+    round_trip(&a, &b);
+    // identical frames
+    assert_eq!(round_trip(&a, &a), [0xE8, 0x03, 0, 0]);
+    // every byte different
+    let inverted: Vec<u8> = a.iter().map(|x| !x).collect();
+    round_trip(&a, &inverted);
+    round_trip(&[], &[]);
+}
+
+#[test]
+fn xor_splits_runs_longer_than_u16() {
+    let len = u16::MAX as usize * 2 + 10;
+    let a = vec![0; len];
+    let mut b = a.clone();
+    b[len - 1] = 1;
+    // 2 full zero runs and a short one, then the single changed byte
+    let encoded = round_trip(&a, &b);
+    assert_eq!(encoded.len(), 3 * 4 + 1);
+
+    // literal runs are split the same way
+    let different = vec![0xFF; len];
+    round_trip(&a, &different);
+}
+
+#[test]
+fn steps_back_through_frames() {
+    let mut rewind = Rewind::new(10);
+    for pc in [0x200, 0x202, 0x204] {
+        rewind.push(&snapshot_with_pc(pc));
+    }
+    assert_eq!(rewind.frames(), 2);
+    assert!(matches!(rewind.deltas.back(), Some(Delta::Xor(_))));
+    assert_eq!(rewind.pop().unwrap().pc, 0x202);
+    assert_eq!(rewind.pop().unwrap().pc, 0x200);
+    assert!(rewind.pop().is_none());
+}
+
+#[test]
+fn stores_whole_frames_across_a_resolution_change() {
+    let mut em = Chip8::new();
+    // 00FF switches to hires, which makes the display 4 times the size
+    em.load_rom_bytes(&[0x00, 0xFF]).unwrap();
+    let lores = em.snapshot();
+    em.step().unwrap();
+    let hires = em.snapshot();
+    assert_ne!(hires.planes.len(), lores.planes.len());
+
+    let mut rewind = Rewind::new(10);
+    rewind.push(&lores);
+    rewind.push(&hires);
+    assert!(matches!(rewind.deltas.back(), Some(Delta::Full(_))));
+    assert_eq!(rewind.pop().unwrap(), lores);
+}
+
+#[test]
+fn drops_the_oldest_frames_once_full() {
+    let mut rewind = Rewind::new(3);
+    for pc in 0..6 {
+        rewind.push(&snapshot_with_pc(0x200 + pc * 2));
+    }
+    assert_eq!(rewind.frames(), 3);
+    let pcs: Vec<usize> = std::iter::from_fn(|| rewind.pop()).map(|s| s.pc).collect();
+    assert_eq!(pcs, [0x208, 0x206, 0x204]);
+}
+
+#[test]
+fn records_nothing_without_capacity() {
+    let mut rewind = Rewind::new(0);
+    rewind.push(&snapshot_with_pc(0x200));
+    rewind.push(&snapshot_with_pc(0x202));
+    assert_eq!(rewind.frames(), 0);
+    assert!(rewind.pop().is_none());
+}
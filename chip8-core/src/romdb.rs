@@ -0,0 +1,211 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+
+use crate::quirks::{Platform, Quirks};
+
+#[cfg(test)]
+mod tests;
+
+const BUNDLED_PROGRAMS: &str = include_str!("../database/programs.json");
+const BUNDLED_PLATFORMS: &str = include_str!("../database/platforms.json");
+
+/// Known roms, looked up by the SHA-1 of their bytes. Reads the
+/// ```programs.json``` and ```platforms.json``` files of the CHIP-8 community
+/// database, so a full copy of it can be used in place of the bundled one.
+#[derive(Debug, Clone)]
+pub struct RomDatabase {
+    programs: Vec<Program>,
+    platforms: Vec<PlatformInfo>,
+    // lowercase hex SHA-1 to index in programs
+    hashes: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Program {
+    pub title: String,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    pub release: Option<String>,
+    #[serde(default)]
+    pub roms: HashMap<String, RomInfo>,
+}
+
+/// One version of a program.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RomInfo {
+    /// Platform ids the rom runs on, best first.
+    #[serde(default)]
+    pub platforms: Vec<String>,
+    /// Platforms the rom runs on with some quirks changed.
+    #[serde(default)]
+    pub quirky_platforms: HashMap<String, QuirkFlags>,
+    /// Instructions per frame.
+    pub tickrate: Option<u32>,
+    /// Which Chip-8 key does what, e.g. ```"left": 4```.
+    #[serde(default)]
+    pub keys: BTreeMap<String, u8>,
+    pub colors: Option<Colors>,
+}
+
+/// Quirks as the database names them. Missing ones aren't changed.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuirkFlags {
+    /// 8XY6 and 8XYE shift V```x``` in place.
+    pub shift: Option<bool>,
+    /// FX55 and FX65 increment I by ```x```.
+    pub memory_increment_by_x: Option<bool>,
+    /// FX55 and FX65 leave I alone.
+    pub memory_leave_i_unchanged: Option<bool>,
+    /// Sprites wrap around the screen edges.
+    pub wrap: Option<bool>,
+    /// BNNN jumps to ```nnn``` plus V```x```.
+    pub jump: Option<bool>,
+    /// DXYN waits for the vertical blank.
+    pub vblank: Option<bool>,
+    /// 8XY1, 8XY2 and 8XY3 reset VF.
+    pub logic: Option<bool>,
+}
+
+/// ```#rrggbb``` colors.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Colors {
+    /// Background, then each combination of planes.
+    #[serde(default)]
+    pub pixels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformInfo {
+    pub id: String,
+    pub name: String,
+    pub default_tickrate: Option<u32>,
+    #[serde(default)]
+    pub quirks: QuirkFlags,
+}
+
+/// What the database knows about a rom.
+#[derive(Debug, Clone, Copy)]
+pub struct RomEntry<'a> {
+    pub program: &'a Program,
+    pub rom: &'a RomInfo,
+    /// The rom's first platform, if the database knows it.
+    pub platform: Option<&'a PlatformInfo>,
+}
+
+impl RomDatabase {
+    /// The database shipped with the interpreter, covering the roms in ```roms/```.
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_PROGRAMS, BUNDLED_PLATFORMS).expect("bundled rom database is valid")
+    }
+
+    pub fn from_json(programs: &str, platforms: &str) -> Result<Self, serde_json::Error> {
+        let programs: Vec<Program> = serde_json::from_str(programs)?;
+        let platforms = serde_json::from_str(platforms)?;
+        let hashes = programs
+            .iter()
+            .enumerate()
+            .flat_map(|(index, program)| {
+                program
+                    .roms
+                    .keys()
+                    .map(move |hash| (hash.to_ascii_lowercase(), index))
+            })
+            .collect();
+        Ok(Self {
+            programs,
+            platforms,
+            hashes,
+        })
+    }
+
+    pub fn lookup(&self, rom_hash: &[u8; 20]) -> Option<RomEntry<'_>> {
+        let hash: String = rom_hash.iter().map(|b| format!("{b:02x}")).collect();
+        let program = &self.programs[*self.hashes.get(&hash)?];
+        let rom = program
+            .roms
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(&hash))
+            .map(|(_, rom)| rom)?;
+        let platform = rom
+            .platforms
+            .first()
+            .and_then(|id| self.platforms.iter().find(|platform| platform.id == *id));
+        Some(RomEntry {
+            program,
+            rom,
+            platform,
+        })
+    }
+}
+
+impl RomEntry<'_> {
+    /// Our closest platform to the rom's, for what the database doesn't cover like stack depth.
+    pub fn base_platform(&self) -> Option<Platform> {
+        match self.platform?.id.as_str() {
+            "originalChip8" | "hybridVIP" | "modernChip8" | "chip8x" => Some(Platform::CosmacVip),
+            "chip48" => Some(Platform::Chip48),
+            "superchip1" | "superchip" | "megachip8" => Some(Platform::SuperChip),
+            "xochip" => Some(Platform::XoChip),
+            _ => None,
+        }
+    }
+
+    /// The platform's quirks, with any the rom changes for that platform on top.
+    pub fn quirks(&self) -> Option<Quirks> {
+        let platform = self.platform?;
+        let mut quirks = self.base_platform()?.quirks();
+        platform.quirks.apply(&mut quirks);
+        if let Some(flags) = self.rom.quirky_platforms.get(&platform.id) {
+            flags.apply(&mut quirks);
+        }
+        Some(quirks)
+    }
+
+    /// Instructions per frame the rom was tuned for, or its platform's default.
+    pub fn tickrate(&self) -> Option<u32> {
+        self.rom
+            .tickrate
+            .or_else(|| self.platform?.default_tickrate)
+    }
+
+    /// Colors for the emulator's palette. Entries the rom doesn't give are left as they are.
+    pub fn palette(&self, mut palette: [u32; 4]) -> [u32; 4] {
+        let pixels = self.rom.colors.as_ref().map(|colors| &colors.pixels[..]);
+        for (color, hex) in palette.iter_mut().zip(pixels.unwrap_or_default()) {
+            if let Ok(rgb) = u32::from_str_radix(hex.trim_start_matches('#'), 16) {
+                *color = rgb;
+            }
+        }
+        palette
+    }
+}
+
+impl QuirkFlags {
+    fn apply(&self, quirks: &mut Quirks) {
+        if let Some(shift) = self.shift {
+            quirks.shift_uses_vy = !shift;
+        }
+        match (self.memory_leave_i_unchanged, self.memory_increment_by_x) {
+            (Some(true), _) => quirks.load_store_increments_i = false,
+            // incrementing by x alone isn't supported, incrementing past x is closer than leaving I
+            (Some(false), _) | (_, Some(true)) => quirks.load_store_increments_i = true,
+            _ => {}
+        }
+        if let Some(wrap) = self.wrap {
+            quirks.clip_sprites = !wrap;
+        }
+        if let Some(jump) = self.jump {
+            quirks.jump_uses_vx = jump;
+        }
+        if let Some(vblank) = self.vblank {
+            quirks.display_wait = vblank;
+        }
+        if let Some(logic) = self.logic {
+            quirks.vf_reset = logic;
+        }
+    }
+}
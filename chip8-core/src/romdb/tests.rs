@@ -0,0 +1,57 @@
+use std::fs;
+
+use super::RomDatabase;
+use crate::emulator::Chip8;
+
+fn hash(path: &str) -> [u8; 20] {
+    let rom = fs::read(format!("{}/../{path}", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let mut em = Box::new(Chip8::new());
+    em.load_rom_bytes(&rom).unwrap();
+    em.rom_hash()
+}
+
+#[test]
+fn knows_every_bundled_rom() {
+    let database = RomDatabase::bundled();
+    let root = format!("{}/../roms", env!("CARGO_MANIFEST_DIR"));
+    for dir in ["games", "tests"] {
+        for file in fs::read_dir(format!("{root}/{dir}")).unwrap() {
+            let name = file.unwrap().file_name().into_string().unwrap();
+            let entry = database.lookup(&hash(&format!("roms/{dir}/{name}")));
+            let entry = entry.unwrap_or_else(|| panic!("{name} isn't in the database"));
+            assert!(entry.quirks().is_some(), "{name} has no known platform");
+            assert!(entry.tickrate().is_some(), "{name} has no tickrate");
+        }
+    }
+}
+
+#[test]
+fn tickrate_falls_back_to_the_platform() {
+    let database = RomDatabase::bundled();
+    let tetris = database.lookup(&hash("roms/games/TETRIS")).unwrap();
+    assert_eq!(tetris.tickrate(), Some(7));
+    let pong = database.lookup(&hash("roms/games/PONG")).unwrap();
+    assert_eq!(pong.tickrate(), Some(15));
+    let blinky = database.lookup(&hash("roms/games/BLINKY")).unwrap();
+    assert_eq!(blinky.tickrate(), Some(30));
+}
+
+#[test]
+fn palette_replaces_only_the_colors_given() {
+    let database = RomDatabase::bundled();
+    let blinky = database.lookup(&hash("roms/games/BLINKY")).unwrap();
+    assert_eq!(blinky.palette([1, 2, 3, 4]), [0x000040, 0xFFFF00, 3, 4]);
+    let pong = database.lookup(&hash("roms/games/PONG")).unwrap();
+    assert_eq!(pong.palette([1, 2, 3, 4]), [1, 2, 3, 4]);
+}
+
+#[test]
+fn unknown_platforms_have_no_tickrate() {
+    let hash = "ab".repeat(20);
+    let programs =
+        format!(r#"[{{"title": "Test", "roms": {{"{hash}": {{"platforms": ["pdp11"]}}}}}}]"#);
+    let database = RomDatabase::from_json(&programs, "[]").unwrap();
+    let entry = database.lookup(&[0xAB; 20]).unwrap();
+    assert!(entry.platform.is_none());
+    assert_eq!(entry.tickrate(), None);
+}
@@ -0,0 +1,306 @@
+use std::{fmt, io};
+
+#[cfg(test)]
+mod tests;
+
+const MAGIC: &[u8; 4] = b"C8SS";
+/// Bumped whenever the layout written by ```Snapshot::to_bytes``` changes.
+pub const SNAPSHOT_VERSION: u16 = 2;
+
+/// Everything needed to put a ```Chip8``` back exactly where it was.
+/// Taken with ```Chip8::snapshot``` and applied with ```Chip8::restore```.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// SHA-1 of the rom the state was taken with.
+    pub rom_hash: [u8; 20],
+    pub memory: Vec<u8>,
+    pub planes: Vec<u8>,
+    pub hires: bool,
+    pub selected_planes: u8,
+    pub stack: Vec<u16>,
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: usize,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub rpl: [u8; 16],
+    pub audio_pattern: [u8; 16],
+    pub pitch: u8,
+    pub exited: bool,
+    pub vblank: bool,
+    /// Keys held at the last timer tick and the one before, which is what instructions see.
+    pub frame_keys: u16,
+    pub previous_frame_keys: u16,
+    /// The key FX0A is waiting to be released.
+    pub key_wait: Option<u8>,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// Not a save state file.
+    BadMagic,
+    /// Written by a newer or older version of the emulator.
+    UnsupportedVersion(u16),
+    /// The file ends before all the state has been read.
+    Truncated,
+    /// The state doesn't fit the machine, e.g. the wrong memory size.
+    Corrupt,
+    /// The state was saved while running a different rom.
+    RomMismatch,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "Couldn't access save state: {e}"),
+            SnapshotError::BadMagic => f.write_str("Not a save state"),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "Save state version {version} isn't supported, expected {SNAPSHOT_VERSION}"
+            ),
+            SnapshotError::Truncated => f.write_str("Save state is truncated"),
+            SnapshotError::Corrupt => f.write_str("Save state is corrupt"),
+            SnapshotError::RomMismatch => f.write_str("Save state is for a different rom"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl Snapshot {
+    /// Serializes the snapshot. Numbers are little endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.memory.len() + self.planes.len() + 128);
+        out.extend(MAGIC);
+        out.extend(SNAPSHOT_VERSION.to_le_bytes());
+        out.extend(self.rom_hash);
+
+        out.extend((self.pc as u32).to_le_bytes());
+        out.extend(self.i.to_le_bytes());
+        out.extend(self.v);
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+        out.push(
+            self.hires as u8
+                | (self.exited as u8) << 1
+                | (self.vblank as u8) << 2
+                | (self.key_wait.is_some() as u8) << 3,
+        );
+        out.push(self.key_wait.unwrap_or(0));
+        out.extend(self.frame_keys.to_le_bytes());
+        out.extend(self.previous_frame_keys.to_le_bytes());
+        out.push(self.selected_planes);
+        out.push(self.pitch);
+        out.extend(self.audio_pattern);
+        out.extend(self.rpl);
+
+        out.extend((self.stack.len() as u16).to_le_bytes());
+        for addr in &self.stack {
+            out.extend(addr.to_le_bytes());
+        }
+        out.extend((self.memory.len() as u32).to_le_bytes());
+        out.extend(&self.memory);
+        out.extend((self.planes.len() as u32).to_le_bytes());
+        out.extend(&self.planes);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut reader = Reader { bytes };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let rom_hash = reader.array()?;
+
+        let pc = reader.u32()? as usize;
+        let i = reader.u16()?;
+        let v = reader.array()?;
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let flags = reader.u8()?;
+        let key_wait = reader.u8()?;
+        if key_wait > 0xF {
+            return Err(SnapshotError::Corrupt);
+        }
+        let frame_keys = reader.u16()?;
+        let previous_frame_keys = reader.u16()?;
+        let selected_planes = reader.u8()?;
+        let pitch = reader.u8()?;
+        let audio_pattern = reader.array()?;
+        let rpl = reader.array()?;
+
+        let stack_len = reader.u16()? as usize;
+        let stack = (0..stack_len)
+            .map(|_| reader.u16())
+            .collect::<Result<_, _>>()?;
+        let memory_len = reader.u32()? as usize;
+        let memory = reader.take(memory_len)?.to_vec();
+        let planes_len = reader.u32()? as usize;
+        let planes = reader.take(planes_len)?.to_vec();
+
+        Ok(Self {
+            rom_hash,
+            memory,
+            planes,
+            hires: flags & 0b001 != 0,
+            selected_planes,
+            stack,
+            v,
+            i,
+            pc,
+            delay_timer,
+            sound_timer,
+            rpl,
+            audio_pattern,
+            pitch,
+            exited: flags & 0b010 != 0,
+            vblank: flags & 0b100 != 0,
+            frame_keys,
+            previous_frame_keys,
+            key_wait: (flags & 0b1000 != 0).then_some(key_wait),
+        })
+    }
+
+    /// Describes every way ```other``` differs from this snapshot, one line each.
+    /// Empty when the states are the same.
+    pub fn diff(&self, other: &Snapshot) -> Vec<String> {
+        let mut differences = Vec::new();
+        let mut compare = |name: &str, before: String, after: String| {
+            if before != after {
+                differences.push(format!("{name}: {before} -> {after}"));
+            }
+        };
+
+        compare(
+            "pc",
+            format!("{:#05X}", self.pc),
+            format!("{:#05X}", other.pc),
+        );
+        compare("i", format!("{:#05X}", self.i), format!("{:#05X}", other.i));
+        for x in 0..16 {
+            compare(
+                &format!("V{x:X}"),
+                format!("{:#04X}", self.v[x]),
+                format!("{:#04X}", other.v[x]),
+            );
+        }
+        compare(
+            "stack",
+            format!("{:X?}", self.stack),
+            format!("{:X?}", other.stack),
+        );
+        compare(
+            "delay_timer",
+            self.delay_timer.to_string(),
+            other.delay_timer.to_string(),
+        );
+        compare(
+            "sound_timer",
+            self.sound_timer.to_string(),
+            other.sound_timer.to_string(),
+        );
+        compare("hires", self.hires.to_string(), other.hires.to_string());
+        compare(
+            "selected_planes",
+            self.selected_planes.to_string(),
+            other.selected_planes.to_string(),
+        );
+        compare(
+            "rpl",
+            format!("{:X?}", self.rpl),
+            format!("{:X?}", other.rpl),
+        );
+        compare(
+            "audio_pattern",
+            format!("{:X?}", self.audio_pattern),
+            format!("{:X?}", other.audio_pattern),
+        );
+        compare("pitch", self.pitch.to_string(), other.pitch.to_string());
+        compare("exited", self.exited.to_string(), other.exited.to_string());
+        compare("vblank", self.vblank.to_string(), other.vblank.to_string());
+        compare(
+            "frame_keys",
+            format!("{:#06X}", self.frame_keys),
+            format!("{:#06X}", other.frame_keys),
+        );
+        compare(
+            "previous_frame_keys",
+            format!("{:#06X}", self.previous_frame_keys),
+            format!("{:#06X}", other.previous_frame_keys),
+        );
+        compare(
+            "key_wait",
+            format!("{:X?}", self.key_wait),
+            format!("{:X?}", other.key_wait),
+        );
+
+        for (addr, (before, after)) in self.memory.iter().zip(&other.memory).enumerate() {
+            compare(
+                &format!("memory[{addr:#05X}]"),
+                format!("{before:#04X}"),
+                format!("{after:#04X}"),
+            );
+        }
+
+        if self.planes.len() != other.planes.len() {
+            compare(
+                "display size",
+                self.planes.len().to_string(),
+                other.planes.len().to_string(),
+            );
+        } else {
+            let changed = self
+                .planes
+                .iter()
+                .zip(&other.planes)
+                .filter(|(before, after)| before != after)
+                .count();
+            compare("changed pixels", "0".to_string(), changed.to_string());
+        }
+
+        differences
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.bytes.len() < len {
+            return Err(SnapshotError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+}
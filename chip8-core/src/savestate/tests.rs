@@ -0,0 +1,181 @@
+use super::{SNAPSHOT_VERSION, Snapshot, SnapshotError};
+use crate::emulator::Chip8;
+
+/// A snapshot with every field set to something other than its default.
+/// Memory and planes are kept short, the format doesn't care about their size.
+fn snapshot() -> Snapshot {
+    Snapshot {
+        rom_hash: [0xAB; 20],
+        memory: (0..32).collect(),
+        planes: vec![0, 1, 2, 3, 0, 1],
+        hires: true,
+        selected_planes: 0b11,
+        stack: vec![0x202, 0x3FE],
+        v: [0x5A; 16],
+        i: 0x123,
+        pc: 0x456,
+        delay_timer: 7,
+        sound_timer: 9,
+        rpl: [0x11; 16],
+        audio_pattern: [0xF0; 16],
+        pitch: 100,
+        exited: true,
+        vblank: true,
+        frame_keys: 0x0120,
+        previous_frame_keys: 0x8001,
+        key_wait: Some(0xC),
+    }
+}
+
+#[test]
+fn round_trips_through_bytes() {
+    let snapshot = snapshot();
+    assert_eq!(
+        Snapshot::from_bytes(&snapshot.to_bytes()).unwrap(),
+        snapshot
+    );
+
+    let defaults = Chip8::new().snapshot();
+    assert_eq!(
+        Snapshot::from_bytes(&defaults.to_bytes()).unwrap(),
+        defaults
+    );
+}
+
+#[test]
+fn rejects_every_truncation() {
+    let bytes = snapshot().to_bytes();
+    for len in 0..bytes.len() {
+        assert!(
+            matches!(
+                Snapshot::from_bytes(&bytes[..len]),
+                Err(SnapshotError::Truncated)
+            ),
+            "{len} of {} bytes wasn't reported as truncated",
+            bytes.len()
+        );
+    }
+}
+
+#[test]
+fn rejects_other_files() {
+    let mut bytes = snapshot().to_bytes();
+    bytes[..4].copy_from_slice(b"PNG\0");
+    assert!(matches!(
+        Snapshot::from_bytes(&bytes),
+        Err(SnapshotError::BadMagic)
+    ));
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut bytes = snapshot().to_bytes();
+    let version = SNAPSHOT_VERSION + 1;
+    bytes[4..6].copy_from_slice(&version.to_le_bytes());
+    assert!(matches!(
+        Snapshot::from_bytes(&bytes),
+        Err(SnapshotError::UnsupportedVersion(v)) if v == version
+    ));
+}
+
+#[test]
+fn restores_only_into_the_same_rom() {
+    let mut em = Chip8::new();
+    em.load_rom_bytes(&[0x60, 0x01]).unwrap();
+    let before = em.snapshot();
+
+    let mut other = Chip8::new();
+    other.load_rom_bytes(&[0x60, 0x02]).unwrap();
+    assert!(matches!(
+        other.restore(&before),
+        Err(SnapshotError::RomMismatch)
+    ));
+    // nothing changed
+    assert_eq!(other.memory()[0x201], 0x02);
+
+    em.step().unwrap();
+    em.restore(&before).unwrap();
+    assert_eq!(em.snapshot(), before);
+}
+
+#[test]
+fn rejects_a_machine_of_the_wrong_size() {
+    let mut em = Chip8::new();
+    let mut snapshot = em.snapshot();
+    snapshot.memory.truncate(0x1000);
+    assert!(matches!(em.restore(&snapshot), Err(SnapshotError::Corrupt)));
+}
+
+#[test]
+fn diffs_what_changed() {
+    let before = snapshot();
+    assert!(before.diff(&before).is_empty());
+
+    let mut after = before.clone();
+    after.pc = 0x458;
+    after.v[3] = 0x00;
+    after.memory[0x10] = 0xFF;
+    after.planes[0] = 1;
+    after.planes[1] = 0;
+    after.vblank = false;
+    after.key_wait = None;
+    assert_eq!(
+        before.diff(&after),
+        [
+            "pc: 0x456 -> 0x458",
+            "V3: 0x5A -> 0x00",
+            "vblank: true -> false",
+            "key_wait: Some(C) -> None",
+            "memory[0x010]: 0x10 -> 0xFF",
+            "changed pixels: 0 -> 2",
+        ]
+    );
+
+    after.planes.push(0);
+    assert!(
+        before
+            .diff(&after)
+            .contains(&"display size: 6 -> 7".to_string())
+    );
+}
+
+#[test]
+fn restores_a_key_wait_in_progress() {
+    // FX0A saw 7 pressed and waits for it to be released
+    let rom = [0xF3, 0x0A];
+    let mut em = Chip8::new();
+    em.load_rom_bytes(&rom).unwrap();
+    em.set_keys(1 << 7);
+    em.decrement_timers();
+    em.step().unwrap();
+    let snapshot = Snapshot::from_bytes(&em.snapshot().to_bytes()).unwrap();
+    assert_eq!(snapshot.key_wait, Some(7));
+
+    let mut restored = Chip8::new();
+    restored.load_rom_bytes(&rom).unwrap();
+    restored.restore(&snapshot).unwrap();
+    assert_eq!(restored.snapshot(), snapshot);
+    restored.decrement_timers();
+    restored.step().unwrap();
+    assert_eq!(
+        restored.pc(),
+        0x202,
+        "releasing the key should finish the wait"
+    );
+    assert_eq!(restored.snapshot().v[3], 7);
+}
+
+#[test]
+fn rejects_stacks_deeper_than_the_machine_allows() {
+    let mut em = Chip8::new();
+    let mut snapshot = em.snapshot();
+    snapshot.stack = vec![0x200; em.quirks.stack_depth + 1];
+    assert!(matches!(em.restore(&snapshot), Err(SnapshotError::Corrupt)));
+
+    em.quirks.stack_depth = 40000;
+    em.quirks.memory_mapped_stack = true;
+    snapshot.stack = vec![0x200; 25];
+    assert!(matches!(em.restore(&snapshot), Err(SnapshotError::Corrupt)));
+    snapshot.stack.pop();
+    em.restore(&snapshot).unwrap();
+}
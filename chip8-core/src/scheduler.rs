@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use crate::{
+    emulator::{Chip8, INSTRUCTIONS_PER_FRAME},
+    error::Chip8Error,
+    timing::VIP_CYCLES_AVAILABLE,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// The delay and sound timers always count down at 60Hz.
+pub const TIMER_HZ: u32 = 60;
+pub const DEFAULT_INSTRUCTIONS_PER_SECOND: u32 = INSTRUCTIONS_PER_FRAME as u32 * TIMER_HZ;
+/// A million instructions a frame, far more than any rom needs.
+pub const MAX_INSTRUCTIONS_PER_SECOND: u32 = 1_000_000 * TIMER_HZ;
+/// The most ```Scheduler::speed``` is allowed to speed time up by.
+pub const MAX_SPEED: f64 = 100.0;
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+// don't try to catch up on more than half a second, e.g. after the window was dragged
+const MAX_PENDING: u128 = NANOS_PER_SECOND * TIMER_HZ as u128 / 2;
+
+/// How many instructions run between timer ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timing {
+    /// Every instruction takes the same time.
+    InstructionsPerSecond(u32),
+    /// Each instruction takes as many machine cycles as it did on the COSMAC VIP,
+    /// and a draw waiting for the vertical blank gives up the rest of the frame.
+    CosmacVip,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing::InstructionsPerSecond(DEFAULT_INSTRUCTIONS_PER_SECOND)
+    }
+}
+
+impl Timing {
+    /// ```instructions``` a second, or None if that's 0 or more than
+    /// ```MAX_INSTRUCTIONS_PER_SECOND```.
+    pub fn per_second(instructions: u32) -> Option<Self> {
+        (1..=MAX_INSTRUCTIONS_PER_SECOND)
+            .contains(&instructions)
+            .then_some(Timing::InstructionsPerSecond(instructions))
+    }
+
+    /// ```instructions``` every 60Hz frame, as the rom database's tickrate
+    /// gives it. None when out of range, see ```per_second```.
+    pub fn per_frame(instructions: u32) -> Option<Self> {
+        instructions
+            .checked_mul(TIMER_HZ)
+            .and_then(Self::per_second)
+    }
+}
+
+/// Paces the emulator against wall clock time, independent of how often
+/// the frontend draws. Time is run in whole 60Hz timer ticks, each running
+/// its share of the instructions per second.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    timing: Timing,
+    /// Multiplies how fast emulated time passes. Above 1 fast forwards,
+    /// below 1 is slow motion. Clamped to ```MAX_SPEED```, and anything
+    /// that isn't a positive number stops time.
+    pub speed: f64,
+    // emulated time not run yet, in nanoseconds times TIMER_HZ so one tick is a whole NANOS_PER_SECOND
+    pending: u128,
+    // instructions owed from earlier ticks, in sixtieths of an instruction
+    pending_instructions: u64,
+    // instructions left to run in the current tick
+    due: u64,
+    // VIP machine cycles left this frame, negative if the last instruction overran
+    cycle_budget: i64,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new(Timing::default())
+    }
+}
+
+impl Scheduler {
+    pub fn new(timing: Timing) -> Self {
+        Self {
+            timing,
+            speed: 1.0,
+            pending: 0,
+            pending_instructions: 0,
+            due: 0,
+            cycle_budget: 0,
+        }
+    }
+
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Runs everything due in ```elapsed``` of wall clock time with ```keys``` held.
+    /// Returns how many timer ticks were run.
+    pub fn advance(
+        &mut self,
+        em: &mut Chip8,
+        elapsed: Duration,
+        keys: u16,
+    ) -> Result<usize, Chip8Error> {
+        // NaN fails the comparison too
+        let speed = if self.speed > 0.0 {
+            self.speed.min(MAX_SPEED)
+        } else {
+            0.0
+        };
+        let emulated = elapsed.mul_f64(speed);
+        self.pending += emulated.as_nanos() * TIMER_HZ as u128;
+        self.pending = self.pending.min(MAX_PENDING);
+
+        let mut ticks = 0;
+        while self.pending >= NANOS_PER_SECOND {
+            self.pending -= NANOS_PER_SECOND;
+            self.tick(em, keys)?;
+            ticks += 1;
+        }
+        Ok(ticks)
+    }
+
+    /// Ticks the timers once and runs the instructions due before the next tick.
+    pub fn tick(&mut self, em: &mut Chip8, keys: u16) -> Result<(), Chip8Error> {
+        self.tick_until(em, keys, |_| false)?;
+        Ok(())
+    }
+
+    /// Like ```tick```, but checks ```stop``` after every instruction and
+    /// returns true as soon as it does.
+    pub fn tick_until(
+        &mut self,
+        em: &mut Chip8,
+        keys: u16,
+        mut stop: impl FnMut(&Chip8) -> bool,
+    ) -> Result<bool, Chip8Error> {
+        self.start_tick(em, keys);
+        while self.tick_has_time(em) {
+            self.run_instruction(em)?;
+            if stop(em) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Runs a single instruction, for stepping through a program. The timers
+    /// tick with ```keys``` held whenever the last tick's instructions have
+    /// all run, so they keep the same pace as they do under ```advance```.
+    pub fn step(&mut self, em: &mut Chip8, keys: u16) -> Result<(), Chip8Error> {
+        // at under 60 instructions a second some ticks run none
+        for _ in 0..TIMER_HZ {
+            if self.tick_has_time(em) {
+                break;
+            }
+            self.start_tick(em, keys);
+        }
+        self.run_instruction(em)
+    }
+
+    fn start_tick(&mut self, em: &mut Chip8, keys: u16) {
+        em.set_keys(keys);
+        em.decrement_timers();
+
+        match self.timing {
+            Timing::InstructionsPerSecond(instructions_per_second) => {
+                self.pending_instructions += u64::from(instructions_per_second);
+                self.due = self.pending_instructions / u64::from(TIMER_HZ);
+                self.pending_instructions %= u64::from(TIMER_HZ);
+            }
+            Timing::CosmacVip => self.cycle_budget += VIP_CYCLES_AVAILABLE as i64,
+        }
+    }
+
+    fn tick_has_time(&self, em: &Chip8) -> bool {
+        match self.timing {
+            Timing::InstructionsPerSecond(_) => self.due > 0,
+            Timing::CosmacVip => self.cycle_budget > 0 && !em.has_exited() && !em.has_halted(),
+        }
+    }
+
+    fn run_instruction(&mut self, em: &mut Chip8) -> Result<(), Chip8Error> {
+        match self.timing {
+            Timing::InstructionsPerSecond(_) => {
+                self.due = self.due.saturating_sub(1);
+                em.step()
+            }
+            Timing::CosmacVip => {
+                em.step()?;
+                if em.is_waiting_for_vblank() {
+                    // the VIP sits idle until the interrupt
+                    self.cycle_budget = 0;
+                } else {
+                    self.cycle_budget -= em.last_cycles() as i64;
+                }
+                Ok(())
+            }
+        }
+    }
+}
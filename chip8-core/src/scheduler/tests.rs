@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use super::{MAX_INSTRUCTIONS_PER_SECOND, MAX_SPEED, Scheduler, Timing};
+use crate::{emulator::Chip8, timing::VIP_CYCLES_AVAILABLE};
+
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// A machine spinning on a jump to itself, so it never stops on its own.
+fn spinning() -> Box<Chip8> {
+    let mut em = Box::new(Chip8::new());
+    em.load_rom_bytes(&[0x12, 0x00]).unwrap();
+    em
+}
+
+/// How many instructions each of ```ticks``` timer ticks runs.
+fn instructions_per_tick(scheduler: &mut Scheduler, ticks: usize) -> Vec<usize> {
+    let mut em = spinning();
+    (0..ticks)
+        .map(|_| {
+            let mut count = 0;
+            scheduler
+                .tick_until(&mut em, 0, |_| {
+                    count += 1;
+                    false
+                })
+                .unwrap();
+            count
+        })
+        .collect()
+}
+
+fn advance(scheduler: &mut Scheduler, elapsed: Duration) -> usize {
+    scheduler.advance(&mut spinning(), elapsed, 0).unwrap()
+}
+
+#[test]
+fn splits_instructions_evenly_between_ticks() {
+    let mut scheduler = Scheduler::default();
+    assert_eq!(instructions_per_tick(&mut scheduler, 3), [12, 12, 12]);
+}
+
+#[test]
+fn carries_the_remainder_over_to_later_ticks() {
+    // 1.5 a tick
+    let mut scheduler = Scheduler::new(Timing::InstructionsPerSecond(90));
+    assert_eq!(instructions_per_tick(&mut scheduler, 4), [1, 2, 1, 2]);
+
+    // one every 6 ticks
+    let mut scheduler = Scheduler::new(Timing::InstructionsPerSecond(10));
+    let counts = instructions_per_tick(&mut scheduler, 12);
+    assert_eq!(counts, [0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1]);
+}
+
+#[test]
+fn runs_the_most_instructions_allowed() {
+    let timing = Timing::per_second(MAX_INSTRUCTIONS_PER_SECOND).unwrap();
+    let mut scheduler = Scheduler::new(timing);
+    assert_eq!(instructions_per_tick(&mut scheduler, 1), [1_000_000]);
+
+    // the instructions owed would overflow a u32 on the second tick
+    let mut scheduler = Scheduler::new(Timing::InstructionsPerSecond(u32::MAX));
+    let mut em = spinning();
+    for _ in 0..2 {
+        assert!(scheduler.tick_until(&mut em, 0, |_| true).unwrap());
+    }
+}
+
+#[test]
+fn rejects_instruction_rates_out_of_range() {
+    assert_eq!(Timing::per_second(0), None);
+    assert_eq!(Timing::per_second(MAX_INSTRUCTIONS_PER_SECOND + 1), None);
+    assert_eq!(
+        Timing::per_frame(15),
+        Some(Timing::InstructionsPerSecond(900))
+    );
+    assert_eq!(Timing::per_frame(u32::MAX), None);
+}
+
+#[test]
+fn ticks_the_timers_once_a_tick() {
+    let mut em = Box::new(Chip8::new());
+    // delay timer = 10, then spin
+    em.load_rom_bytes(&[0x60, 0x0A, 0xF0, 0x15, 0x12, 0x04])
+        .unwrap();
+    let mut scheduler = Scheduler::new(Timing::InstructionsPerSecond(6000));
+    scheduler.tick(&mut em, 0).unwrap();
+    assert_eq!(em.delay_timer(), 10);
+    for _ in 0..4 {
+        scheduler.tick(&mut em, 0).unwrap();
+    }
+    assert_eq!(em.delay_timer(), 6);
+}
+
+#[test]
+fn stops_as_soon_as_asked() {
+    let mut em = spinning();
+    let mut scheduler = Scheduler::default();
+    let mut count = 0;
+    let stopped = scheduler
+        .tick_until(&mut em, 0, |_| {
+            count += 1;
+            count == 5
+        })
+        .unwrap();
+    assert!(stopped);
+    assert_eq!(count, 5);
+}
+
+#[test]
+fn advances_in_whole_ticks_and_keeps_the_rest() {
+    let mut scheduler = Scheduler::default();
+    assert_eq!(advance(&mut scheduler, FRAME / 2), 0);
+    assert_eq!(advance(&mut scheduler, FRAME / 2), 0);
+    // FRAME is rounded down, so the two halves leave it just short
+    assert_eq!(advance(&mut scheduler, Duration::from_micros(1)), 1);
+    assert_eq!(advance(&mut scheduler, FRAME * 3), 3);
+}
+
+#[test]
+fn catches_up_on_at_most_half_a_second() {
+    let mut scheduler = Scheduler::default();
+    assert_eq!(advance(&mut scheduler, Duration::from_secs(10)), 30);
+    // nothing left over from the time that was dropped
+    assert_eq!(advance(&mut scheduler, FRAME / 2), 0);
+}
+
+#[test]
+fn speed_scales_time() {
+    let mut scheduler = Scheduler {
+        speed: 2.0,
+        ..Scheduler::default()
+    };
+    assert_eq!(advance(&mut scheduler, Duration::from_millis(100)), 12);
+    scheduler.speed = 0.5;
+    assert_eq!(advance(&mut scheduler, Duration::from_millis(100)), 3);
+}
+
+#[test]
+fn speed_is_clamped() {
+    let mut scheduler = Scheduler {
+        speed: MAX_SPEED * 1000.0,
+        ..Scheduler::default()
+    };
+    assert_eq!(advance(&mut scheduler, Duration::from_millis(1)), 6);
+    scheduler.speed = f64::INFINITY;
+    assert_eq!(advance(&mut scheduler, Duration::from_secs(1)), 30);
+
+    for speed in [f64::NAN, 0.0, -1.0] {
+        scheduler.speed = speed;
+        assert_eq!(
+            advance(&mut scheduler, Duration::from_secs(1)),
+            0,
+            "speed {speed}"
+        );
+    }
+}
+
+#[test]
+fn vip_timing_carries_overrun_cycles_into_the_next_frame() {
+    let mut scheduler = Scheduler::new(Timing::CosmacVip);
+    let counts = instructions_per_tick(&mut scheduler, 2);
+
+    let mut em = spinning();
+    em.step().unwrap();
+    let cycles = em.last_cycles() as usize;
+    let available = VIP_CYCLES_AVAILABLE as usize;
+    assert_eq!(counts[0], available.div_ceil(cycles));
+    assert_eq!(counts[0] + counts[1], (2 * available).div_ceil(cycles));
+}
+
+#[test]
+fn stepping_ticks_the_timers_at_the_same_rate() {
+    let mut em = Box::new(Chip8::new());
+    // delay timer = 10, then spin
+    em.load_rom_bytes(&[0x60, 0x0A, 0xF0, 0x15, 0x12, 0x04])
+        .unwrap();
+    let mut scheduler = Scheduler::new(Timing::InstructionsPerSecond(120));
+    for _ in 0..2 {
+        scheduler.step(&mut em, 0).unwrap();
+    }
+    assert_eq!(em.delay_timer(), 10);
+    // two instructions a tick
+    for _ in 0..4 {
+        scheduler.step(&mut em, 0).unwrap();
+    }
+    assert_eq!(em.delay_timer(), 8);
+}
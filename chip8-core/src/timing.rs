@@ -0,0 +1,84 @@
+use crate::instruction::Instruction;
+
+/// The VIP's 1802 runs at 1.7609MHz with 8 clocks per machine cycle,
+/// which is 3668 machine cycles per 60Hz frame.
+pub const VIP_CYCLES_PER_FRAME: u32 = 3668;
+/// Cycles taken each frame by the display DMA (128 lines of 8 bytes)
+/// and the interrupt routine that runs it and the timers.
+const VIP_INTERRUPT_CYCLES: u32 = 1024 + 46;
+/// Cycles left for running Chip-8 instructions each frame.
+pub const VIP_CYCLES_AVAILABLE: u32 = VIP_CYCLES_PER_FRAME - VIP_INTERRUPT_CYCLES;
+
+// every instruction is fetched and dispatched before it runs
+const FETCH_DECODE: u32 = 40;
+
+/// Approximate machine cycles the COSMAC VIP interpreter takes to run an instruction.
+///
+/// ```v``` are the registers before it ran, since drawing and BCD
+/// take longer for some values. ```skipped``` is whether a skip was taken.
+pub fn cosmac_vip_cycles(instruction: Instruction, v: &[u8; 16], skipped: bool) -> u32 {
+    use Instruction::*;
+
+    let skip = |not_taken, taken| if skipped { taken } else { not_taken };
+    let execute = match instruction {
+        // loops over all 256 bytes of display memory
+        Clear => 3078,
+        Return => 10,
+        Jump { .. } => 12,
+        Call { .. } => 26,
+        SkipIfEqual { .. } | SkipIfNotEqual { .. } | SkipIfKey { .. } | SkipIfNotKey { .. } => {
+            skip(10, 14)
+        }
+        SkipIfRegistersEqual { .. } | SkipIfRegistersNotEqual { .. } => skip(14, 18),
+        Set { .. } => 6,
+        Add { .. } => 10,
+        Copy { .. } => 12,
+        Or { .. }
+        | And { .. }
+        | Xor { .. }
+        | AddRegisters { .. }
+        | Subtract { .. }
+        | ShiftRight { .. }
+        | SubtractReversed { .. }
+        | ShiftLeft { .. } => 44,
+        SetIndex { .. } => 12,
+        JumpWithOffset { .. } => 22,
+        Random { .. } => 36,
+        Draw { x, n, .. } => draw_cycles(v[x], n),
+        GetDelayTimer { .. } | SetDelayTimer { .. } | SetSoundTimer { .. } => 10,
+        WaitForKey { .. } => 18,
+        AddToIndex { .. } => 16,
+        Font { .. } | BigFont { .. } => 16,
+        // converts by repeated subtraction, so bigger digits take longer
+        Bcd { x } => {
+            let digits = [v[x] / 100, v[x] / 10 % 10, v[x] % 10];
+            80 + 16 * digits.iter().map(|d| *d as u32).sum::<u32>()
+        }
+        Store { x } | Load { x } | SaveFlags { x } | LoadFlags { x } => 14 + 14 * (x as u32 + 1),
+        SaveRange { x, y } | LoadRange { x, y } => 14 + 14 * (x.abs_diff(y) as u32 + 1),
+        // not part of the VIP interpreter
+        ScrollDown { .. }
+        | ScrollUp { .. }
+        | ScrollRight
+        | ScrollLeft
+        | Exit
+        | Lores
+        | Hires
+        | Sys { .. }
+        | LoadLongIndex
+        | SelectPlanes { .. }
+        | LoadAudioPattern
+        | SetPitch { .. }
+        | Unknown { .. } => 10,
+    };
+    FETCH_DECODE + execute
+}
+
+/// Sprites drawn at an x that isn't a multiple of 8 straddle two bytes
+/// of display memory, and each row is shifted into place a bit at a time.
+fn draw_cycles(x: u8, n: usize) -> u32 {
+    let rows = if n == 0 { 32 } else { n as u32 };
+    let shift = (x % 8) as u32;
+    let row_cycles = if shift == 0 { 22 } else { 36 + 2 * shift };
+    26 + rows * row_cycles
+}
@@ -0,0 +1,213 @@
+//! Runs the test roms in ```roms/tests``` headless and compares the final
+//! display against the golden images in ```tests/golden```.
+//!
+//! After an intended change to what a rom shows, rewrite the golden images with
+//! ```UPDATE_GOLDEN=1 cargo test -p chip8-core --test conformance``` and check them by eye.
+
+use std::{env, fs, path::PathBuf};
+
+use chip8_core::{
+    audio::{AudioEvent, RecordingSink},
+    emulator::Chip8,
+    headless::{Headless, ImageFormat, StopReason, encode_display},
+    quirks::Platform,
+    romdb::RomDatabase,
+};
+
+struct Run {
+    rom: &'static str,
+    frames: usize,
+    /// ```key@frame[-frame]```, as for ```--press```.
+    presses: &'static [&'static str],
+    /// Overrides the rom database.
+    platform: Option<Platform>,
+}
+
+impl Run {
+    fn new(rom: &'static str, frames: usize) -> Self {
+        Self {
+            rom,
+            frames,
+            presses: &[],
+            platform: None,
+        }
+    }
+
+    fn press(self, presses: &'static [&'static str]) -> Self {
+        Self { presses, ..self }
+    }
+
+    fn platform(self, platform: Platform) -> Self {
+        Self {
+            platform: Some(platform),
+            ..self
+        }
+    }
+
+    fn run(&self, em: &mut Chip8) {
+        let path = repo_dir().join("roms/tests").join(self.rom);
+        em.load_rom_bytes(&fs::read(&path).unwrap()).unwrap();
+        em.quirks = match self.platform {
+            Some(platform) => platform.quirks(),
+            None => RomDatabase::bundled()
+                .lookup(&em.rom_hash())
+                .and_then(|rom| rom.quirks())
+                .unwrap_or_default(),
+        };
+
+        let headless = Headless {
+            frames: self.frames,
+            presses: self.presses.iter().map(|p| p.parse().unwrap()).collect(),
+            ..Headless::default()
+        };
+        let (frames, reason) = headless.run(em);
+        if let StopReason::Error(e) = reason {
+            panic!("{} failed after {frames} frames: {e}", self.rom);
+        }
+    }
+
+    /// Runs the rom and checks the display against ```tests/golden/<golden>.pbm```.
+    fn check(&self, golden: &str) {
+        let mut em = Chip8::new();
+        self.run(&mut em);
+
+        let actual = encode_display(&em, ImageFormat::Pbm).unwrap();
+        let path = golden_dir().join(golden).with_extension("pbm");
+        if env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(golden_dir()).unwrap();
+            fs::write(&path, &actual).unwrap();
+            return;
+        }
+
+        let expected = fs::read(&path)
+            .unwrap_or_else(|e| panic!("{}: {e}, run with UPDATE_GOLDEN=1", path.display()));
+        if actual != expected {
+            let ascii = encode_display(&em, ImageFormat::Ascii).unwrap();
+            panic!(
+                "{} doesn't match {}, it shows:\n{}",
+                self.rom,
+                path.display(),
+                String::from_utf8_lossy(&ascii)
+            );
+        }
+    }
+}
+
+fn repo_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+#[test]
+fn chip8_logo() {
+    Run::new("1-chip8-logo.ch8", 60).check("1-chip8-logo");
+}
+
+#[test]
+fn ibm_logo() {
+    Run::new("2-ibm-logo.ch8", 60).check("2-ibm-logo");
+}
+
+#[test]
+fn corax() {
+    Run::new("3-corax.ch8", 120).check("3-corax");
+}
+
+#[test]
+fn flags() {
+    Run::new("4-flags.ch8", 300).check("4-flags");
+}
+
+// the menu takes a couple of seconds to draw on the VIP before it reads keys
+
+#[test]
+fn keypad_ex9e() {
+    // 5 should be the only key lit while it's held
+    Run::new("5-keypad.ch8", 400)
+        .press(&["1@150-153", "5@300-400"])
+        .check("5-keypad-ex9e");
+}
+
+#[test]
+fn keypad_exa1() {
+    // every key but 5 should be lit while it's held
+    Run::new("5-keypad.ch8", 400)
+        .press(&["2@150-153", "5@300-400"])
+        .check("5-keypad-exa1");
+}
+
+#[test]
+fn keypad_fx0a() {
+    Run::new("5-keypad.ch8", 400)
+        .press(&["3@150-153", "5@300-303"])
+        .check("5-keypad-fx0a");
+}
+
+#[test]
+fn beep() {
+    let sink = RecordingSink::new();
+    let mut em = Chip8::with_audio(Box::new(sink.clone()));
+    // tapping B stops the tune, after which it beeps while B is held
+    Run::new("6-beep.ch8", 60).press(&["B@30-31"]).run(&mut em);
+    assert!(
+        sink.events().contains(&AudioEvent::StartTone),
+        "the tune didn't play"
+    );
+
+    sink.clear();
+    let (_, reason) = Headless {
+        frames: 60,
+        presses: vec!["B@10-40".parse().unwrap()],
+        ..Headless::default()
+    }
+    .run(&mut em);
+    assert!(matches!(reason, StopReason::FrameLimit));
+    assert_eq!(
+        sink.events(),
+        [AudioEvent::StartTone, AudioEvent::StopTone],
+        "expected one beep while B was held"
+    );
+}
+
+#[test]
+fn test_opcode() {
+    Run::new("7-test_opcode.ch8", 60).check("7-test_opcode");
+}
+
+#[test]
+fn metteo_test() {
+    // registers start out zeroed
+    Run::new("8-metteo-test.ch8", 60).check("8-metteo-test");
+}
+
+#[test]
+fn quirks_chip8() {
+    Run::new("9-quirks.ch8", 1200)
+        .press(&["1@100-103"])
+        .check("9-quirks-chip8");
+}
+
+#[test]
+fn quirks_superchip() {
+    // 2 picks SUPER-CHIP, then 1 the modern variant
+    Run::new("9-quirks.ch8", 1200)
+        .press(&["2@100-103", "1@200-203"])
+        .platform(Platform::SuperChip)
+        .check("9-quirks-superchip");
+}
+
+#[test]
+fn quirks_xochip() {
+    Run::new("9-quirks.ch8", 1200)
+        .press(&["3@100-103"])
+        .platform(Platform::XoChip)
+        .check("9-quirks-xochip");
+}
+
+#[test]
+fn bc_test() {
+    Run::new("BC_test.ch8", 60).check("BC_test");
+}
@@ -0,0 +1,92 @@
+//! WebAssembly bindings for ```chip8_core```, so roms can run in a browser.
+//! The page in ```www/``` draws them to a canvas, reads the keyboard and
+//! plays the beep through WebAudio.
+
+use chip8_core::{
+    emulator::Chip8,
+    romdb::RomDatabase,
+    scheduler::{Scheduler, Timing},
+};
+use wasm_bindgen::prelude::*;
+
+/// A Chip-8 machine, run a 60Hz frame at a time by the page.
+#[wasm_bindgen]
+pub struct Emulator {
+    em: Box<Chip8>,
+    scheduler: Scheduler,
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            em: Box::new(Chip8::new()),
+            scheduler: Scheduler::default(),
+        }
+    }
+
+    /// Resets the machine and loads ```bytes``` at 0x200. Roms the bundled
+    /// database knows get their platform's quirks, speed and colors.
+    pub fn load_rom(&mut self, bytes: &[u8]) -> Result<(), JsError> {
+        let mut em = Box::new(Chip8::new());
+        em.load_rom_bytes(bytes)?;
+
+        let database = RomDatabase::bundled();
+        let rom = database.lookup(&em.rom_hash());
+        let mut timing = Timing::default();
+        if let Some(rom) = rom {
+            em.quirks = rom.quirks().unwrap_or_default();
+            em.palette = rom.palette(em.palette);
+            if let Some(per_frame) = rom.tickrate().and_then(Timing::per_frame) {
+                timing = per_frame;
+            }
+        }
+
+        self.em = em;
+        self.scheduler = Scheduler::new(timing);
+        Ok(())
+    }
+
+    /// Ticks the timers and runs a frame's worth of instructions with the
+    /// keys set by ```set_key```.
+    pub fn run_frame(&mut self) -> Result<(), JsError> {
+        let keys = self.em.keys();
+        self.scheduler.tick(&mut self.em, keys)?;
+        Ok(())
+    }
+
+    /// Presses or releases Chip-8 key ```key``` (0-F).
+    pub fn set_key(&mut self, key: u8, down: bool) {
+        self.em.set_key(key, down);
+    }
+
+    /// The display as 0RGB pixels, ```width()``` by ```height()```.
+    pub fn framebuffer(&self) -> Vec<u32> {
+        self.em.framebuffer().to_vec()
+    }
+
+    pub fn width(&self) -> usize {
+        self.em.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.em.height()
+    }
+
+    /// True while the sound timer is running, which is when the beep plays.
+    pub fn beeping(&self) -> bool {
+        self.em.sound_timer() > 0
+    }
+
+    /// True once the rom has exited with 00FD.
+    pub fn has_exited(&self) -> bool {
+        self.em.has_exited()
+    }
+}
@@ -0,0 +1,93 @@
+//! Drives the bindings the way the page does, under Node:
+//! ```cargo test -p chip8-web --target wasm32-unknown-unknown```
+#![cfg(target_arch = "wasm32")]
+
+use chip8_web::Emulator;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const IBM_LOGO: &[u8] = include_bytes!("../../roms/tests/2-ibm-logo.ch8");
+const KEYPAD: &[u8] = include_bytes!("../../roms/tests/5-keypad.ch8");
+
+/// Compares the display with a PBM golden image from the conformance suite.
+fn assert_shows(emulator: &Emulator, golden: &str) {
+    let mut tokens = golden.split_whitespace().skip(1);
+    let width: usize = tokens.next().unwrap().parse().unwrap();
+    let height: usize = tokens.next().unwrap().parse().unwrap();
+    assert_eq!((emulator.width(), emulator.height()), (width, height));
+
+    let expected: Vec<bool> = tokens.map(|pixel| pixel == "1").collect();
+    let actual: Vec<bool> = emulator
+        .framebuffer()
+        .iter()
+        .map(|color| *color != 0)
+        .collect();
+    assert!(actual == expected, "the display doesn't match");
+}
+
+#[wasm_bindgen_test]
+fn draws_the_ibm_logo() {
+    let mut emulator = Emulator::new();
+    emulator.load_rom(IBM_LOGO).unwrap();
+    for _ in 0..60 {
+        emulator.run_frame().unwrap();
+    }
+    assert_shows(
+        &emulator,
+        include_str!("../../chip8-core/tests/golden/2-ibm-logo.pbm"),
+    );
+}
+
+#[wasm_bindgen_test]
+fn keys_reach_the_rom() {
+    let mut emulator = Emulator::new();
+    emulator.load_rom(KEYPAD).unwrap();
+    // picks the EX9E test, then holds 5, which should be the only key lit
+    for frame in 0..400 {
+        emulator.set_key(0x1, (150..153).contains(&frame));
+        emulator.set_key(0x5, frame >= 300);
+        emulator.run_frame().unwrap();
+    }
+    assert_shows(
+        &emulator,
+        include_str!("../../chip8-core/tests/golden/5-keypad-ex9e.pbm"),
+    );
+}
+
+#[wasm_bindgen_test]
+fn beeps_while_the_sound_timer_runs() {
+    let mut emulator = Emulator::new();
+    // V0 = 5, sound timer = V0, then loop forever
+    emulator
+        .load_rom(&[0x60, 0x05, 0xF0, 0x18, 0x12, 0x04])
+        .unwrap();
+    emulator.run_frame().unwrap();
+    assert!(emulator.beeping());
+    for _ in 0..5 {
+        emulator.run_frame().unwrap();
+    }
+    assert!(!emulator.beeping());
+}
+
+#[wasm_bindgen_test]
+fn loading_resets_the_machine() {
+    let mut emulator = Emulator::new();
+    emulator.load_rom(IBM_LOGO).unwrap();
+    emulator.run_frame().unwrap();
+    emulator.load_rom(&[0x12, 0x00]).unwrap();
+    assert!(emulator.framebuffer().iter().all(|color| *color == 0));
+}
+
+#[wasm_bindgen_test]
+fn rejects_roms_that_dont_fit() {
+    let mut emulator = Emulator::new();
+    // memory is 64K, less the 0x200 bytes before the program
+    assert!(emulator.load_rom(&[0; 0x10000]).is_err());
+}
+
+#[wasm_bindgen_test]
+fn errors_halt_the_frame() {
+    let mut emulator = Emulator::new();
+    // 00EE with an empty stack
+    emulator.load_rom(&[0x00, 0xEE]).unwrap();
+    assert!(emulator.run_frame().is_err());
+}
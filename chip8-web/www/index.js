@@ -0,0 +1,135 @@
+// Runs the wasm build of chip8-web on a canvas. Build pkg/ first, see the readme.
+import init, { Emulator } from "./pkg/chip8_web.js";
+
+// The left of a QWERTY keyboard laid out like the keypad, by KeyboardEvent.code
+const KEYS = {
+  Digit1: 0x1, Digit2: 0x2, Digit3: 0x3, Digit4: 0xc,
+  KeyQ: 0x4, KeyW: 0x5, KeyE: 0x6, KeyR: 0xd,
+  KeyA: 0x7, KeyS: 0x8, KeyD: 0x9, KeyF: 0xe,
+  KeyZ: 0xa, KeyX: 0x0, KeyC: 0xb, KeyV: 0xf,
+};
+const FRAME_MS = 1000 / 60;
+// a backgrounded tab stops animating, don't try to catch up on all of it
+const MAX_PENDING_MS = 250;
+
+// A quiet 440Hz square wave, the same as the desktop default.
+class Beeper {
+  constructor() {
+    this.context = new AudioContext();
+    this.gain = this.context.createGain();
+    this.gain.gain.value = 0;
+    this.gain.connect(this.context.destination);
+    const oscillator = this.context.createOscillator();
+    oscillator.type = "square";
+    oscillator.frequency.value = 440;
+    oscillator.connect(this.gain);
+    oscillator.start();
+    this.on = false;
+  }
+
+  set(on) {
+    if (on !== this.on) {
+      // a short ramp instead of a jump avoids clicks
+      this.gain.gain.setTargetAtTime(on ? 0.1 : 0, this.context.currentTime, 0.005);
+      this.on = on;
+    }
+  }
+}
+
+await init();
+const emulator = new Emulator();
+const canvas = document.getElementById("screen");
+const context = canvas.getContext("2d");
+const status = document.getElementById("status");
+const beeper = new Beeper();
+let image = context.createImageData(canvas.width, canvas.height);
+let running = false;
+
+function loadRom(name, bytes) {
+  try {
+    emulator.load_rom(bytes);
+    running = true;
+    status.textContent = name;
+  } catch (e) {
+    running = false;
+    status.textContent = e.message;
+  }
+}
+
+document.getElementById("rom").addEventListener("change", async (event) => {
+  const file = event.target.files[0];
+  if (file) {
+    loadRom(file.name, new Uint8Array(await file.arrayBuffer()));
+  }
+});
+
+const url = new URLSearchParams(location.search).get("rom");
+if (url) {
+  const response = await fetch(url);
+  if (response.ok) {
+    loadRom(url, new Uint8Array(await response.arrayBuffer()));
+  } else {
+    status.textContent = `Couldn't load ${url}: ${response.status}`;
+  }
+}
+
+function onKey(event, down) {
+  const key = KEYS[event.code];
+  if (key === undefined || event.ctrlKey || event.metaKey || event.altKey) {
+    return;
+  }
+  event.preventDefault();
+  emulator.set_key(key, down);
+  // browsers only let audio start after the user does something
+  beeper.context.resume();
+}
+window.addEventListener("keydown", (event) => onKey(event, true));
+window.addEventListener("keyup", (event) => onKey(event, false));
+// keys released while the page is in the background never send keyup
+window.addEventListener("blur", () => {
+  for (const key of Object.values(KEYS)) {
+    emulator.set_key(key, false);
+  }
+});
+
+function draw() {
+  const width = emulator.width();
+  const height = emulator.height();
+  if (canvas.width !== width || canvas.height !== height) {
+    canvas.width = width;
+    canvas.height = height;
+    image = context.createImageData(width, height);
+  }
+  const pixels = emulator.framebuffer();
+  for (let i = 0; i < pixels.length; i++) {
+    image.data[i * 4] = pixels[i] >> 16;
+    image.data[i * 4 + 1] = (pixels[i] >> 8) & 0xff;
+    image.data[i * 4 + 2] = pixels[i] & 0xff;
+    image.data[i * 4 + 3] = 0xff;
+  }
+  context.putImageData(image, 0, 0);
+}
+
+// runs frames at 60Hz whatever the display's refresh rate
+let pending = 0;
+let last = performance.now();
+function frame(now) {
+  pending = Math.min(pending + now - last, MAX_PENDING_MS);
+  last = now;
+  while (running && pending >= FRAME_MS) {
+    pending -= FRAME_MS;
+    try {
+      emulator.run_frame();
+    } catch (e) {
+      running = false;
+      status.textContent = `Halted: ${e.message}`;
+    }
+    if (emulator.has_exited()) {
+      running = false;
+    }
+  }
+  draw();
+  beeper.set(running && emulator.beeping());
+  requestAnimationFrame(frame);
+}
+requestAnimationFrame(frame);
@@ -0,0 +1,275 @@
+use std::{env, path::PathBuf, process::exit};
+
+use chip8::beep::Tone;
+
+use crate::tty::Style;
+use chip8_core::{
+    emulator::MAX_MEMORY_MAPPED_STACK_DEPTH,
+    error::ErrorPolicy,
+    headless::{Headless, KeyPress},
+    quirks::Platform,
+    scheduler::{MAX_SPEED, Timing},
+};
+
+const USAGE: &str = "\
+Usage: chip8 [--platform <vip|chip48|schip|xochip>] [--on-error <halt|log|ignore>]
+             [--stack-depth <n>] [--memory-mapped-stack]
+             [--ips <n> | --cycles-per-frame <n> | --vip-timing] [--speed <multiplier>]
+             [--rewind-seconds <n>] [--debug | --tty [--braille]] [--config <file.toml|file.json>] [--print-keymap]
+             [--no-database]
+             [--beep-frequency <hz>] [--beep-volume <0-1>] [--beep-waveform <square|sine|triangle>]
+             <path_to_rom>
+       chip8 --headless [--frames <n>] [--until-pc <addr>] [--press <key>@<frame>[-<frame>]]...
+             [--screenshot <file.png|file.pbm|file.txt>] <path_to_rom>";
+const DEFAULT_REWIND_SECONDS: usize = 30;
+const DEFAULT_HEADLESS_FRAMES: usize = 600;
+
+pub struct Args {
+    pub rom_path: PathBuf,
+    /// Overrides the platform from the rom database.
+    pub platform: Option<Platform>,
+    /// Step through the rom in a terminal debugger instead of opening a window.
+    pub debug: bool,
+    /// Draw in the terminal instead of opening a window, e.g. over SSH.
+    pub tty: Option<Style>,
+    /// How far back holding Backspace can rewind. 0 turns rewinding off.
+    pub rewind_seconds: usize,
+    /// Run without a window or audio. See ```--headless``` in the usage.
+    pub headless: Option<Headless>,
+    /// Where a headless run writes the final display. Printed as ASCII when not set.
+    pub screenshot: Option<PathBuf>,
+    pub tone: Tone,
+    /// What to do when the rom does something invalid.
+    pub error_policy: ErrorPolicy,
+    /// Overrides the platform's call stack depth.
+    pub stack_depth: Option<usize>,
+    /// Keep the call stack in memory at 0xEA0 like the COSMAC VIP.
+    pub memory_mapped_stack: bool,
+    /// Overrides the tickrate from the rom database.
+    pub timing: Option<Timing>,
+    /// Above 1 runs faster than real time, below 1 is slow motion.
+    pub speed: f64,
+    /// Read settings from here instead of the default config file.
+    pub config: Option<PathBuf>,
+    /// Print the rom's key bindings and exit.
+    pub print_keymap: bool,
+    /// Don't look the rom up in the bundled rom database.
+    pub no_database: bool,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut rom_path = None;
+        let mut platform = None;
+        let mut debug = false;
+        let mut tty = false;
+        let mut braille = false;
+        let mut rewind_seconds = DEFAULT_REWIND_SECONDS;
+        let mut headless = false;
+        let mut frames = DEFAULT_HEADLESS_FRAMES;
+        let mut until_pc = None;
+        let mut presses = Vec::new();
+        let mut screenshot = None;
+        let mut tone = Tone::default();
+        let mut error_policy = ErrorPolicy::default();
+        let mut stack_depth = None;
+        let mut memory_mapped_stack = false;
+        let mut timing = None;
+        let mut speed = 1.0;
+        let mut config = None;
+        let mut print_keymap = false;
+        let mut no_database = false;
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--platform" | "-p" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--platform needs a value"));
+                    platform = Some(value.parse().unwrap_or_else(|e: String| fail(&e)));
+                }
+                "--on-error" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--on-error needs a value"));
+                    error_policy = value.parse().unwrap_or_else(|e: String| fail(&e));
+                }
+                "--stack-depth" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--stack-depth needs a value"));
+                    stack_depth = Some(
+                        value
+                            .parse()
+                            .unwrap_or_else(|_| fail(&format!("Invalid stack depth {value}"))),
+                    );
+                }
+                "--memory-mapped-stack" => memory_mapped_stack = true,
+                "--ips" => {
+                    let value = args.next().unwrap_or_else(|| fail("--ips needs a value"));
+                    timing = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .and_then(Timing::per_second)
+                            .unwrap_or_else(|| {
+                                fail(&format!("Invalid instructions per second {value}"))
+                            }),
+                    );
+                }
+                "--cycles-per-frame" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--cycles-per-frame needs a value"));
+                    timing = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .and_then(Timing::per_frame)
+                            .unwrap_or_else(|| fail(&format!("Invalid cycles per frame {value}"))),
+                    );
+                }
+                "--vip-timing" => timing = Some(Timing::CosmacVip),
+                "--speed" => {
+                    let value = args.next().unwrap_or_else(|| fail("--speed needs a value"));
+                    speed = value
+                        .parse()
+                        .ok()
+                        .filter(|speed: &f64| {
+                            speed.is_finite() && *speed > 0.0 && *speed <= MAX_SPEED
+                        })
+                        .unwrap_or_else(|| fail(&format!("Invalid speed {value}")));
+                }
+                "--debug" | "-d" => debug = true,
+                "--tty" => tty = true,
+                "--braille" => braille = true,
+                "--config" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--config needs a value"));
+                    config = Some(PathBuf::from(value));
+                }
+                "--print-keymap" => print_keymap = true,
+                "--no-database" => no_database = true,
+                "--rewind-seconds" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--rewind-seconds needs a value"));
+                    rewind_seconds = value
+                        .parse()
+                        .unwrap_or_else(|_| fail(&format!("Invalid number of seconds {value}")));
+                }
+                "--headless" => headless = true,
+                "--frames" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--frames needs a value"));
+                    frames = value
+                        .parse()
+                        .unwrap_or_else(|_| fail(&format!("Invalid number of frames {value}")));
+                }
+                "--until-pc" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--until-pc needs a value"));
+                    let addr = match value.strip_prefix("0x") {
+                        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+                        None => value.parse().ok(),
+                    };
+                    until_pc =
+                        Some(addr.unwrap_or_else(|| fail(&format!("Invalid address {value}"))));
+                }
+                "--press" => {
+                    let value = args.next().unwrap_or_else(|| fail("--press needs a value"));
+                    presses.push(value.parse::<KeyPress>().unwrap_or_else(|e| fail(&e)));
+                }
+                "--screenshot" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--screenshot needs a value"));
+                    screenshot = Some(PathBuf::from(value));
+                }
+                "--beep-frequency" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--beep-frequency needs a value"));
+                    tone.frequency = value
+                        .parse()
+                        .ok()
+                        .filter(|hz: &f64| *hz > 0.0)
+                        .unwrap_or_else(|| fail(&format!("Invalid frequency {value}")));
+                }
+                "--beep-volume" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--beep-volume needs a value"));
+                    tone.volume = value
+                        .parse()
+                        .ok()
+                        .filter(|volume: &f32| (0.0..=1.0).contains(volume))
+                        .unwrap_or_else(|| fail(&format!("Invalid volume {value}")));
+                }
+                "--beep-waveform" => {
+                    let value = args
+                        .next()
+                        .unwrap_or_else(|| fail("--beep-waveform needs a value"));
+                    tone.waveform = value.parse().unwrap_or_else(|e: String| fail(&e));
+                }
+                "--help" | "-h" => {
+                    println!("{USAGE}");
+                    exit(0);
+                }
+                _ if arg.starts_with('-') => fail(&format!("Unknown option {arg}")),
+                _ => rom_path = Some(PathBuf::from(arg)),
+            }
+        }
+
+        let Some(rom_path) = rom_path else {
+            fail("Supply a path to the rom you would like to run. e.g.: chip8 <path_to_rom>");
+        };
+        if memory_mapped_stack
+            && stack_depth.is_some_and(|depth| depth > MAX_MEMORY_MAPPED_STACK_DEPTH)
+        {
+            fail(&format!(
+                "A memory mapped stack holds at most {MAX_MEMORY_MAPPED_STACK_DEPTH} calls"
+            ));
+        }
+
+        Self {
+            rom_path,
+            platform,
+            debug,
+            // --braille is only a tty style, so it implies --tty
+            tty: (tty || braille).then_some(if braille {
+                Style::Braille
+            } else {
+                Style::HalfBlocks
+            }),
+            rewind_seconds,
+            headless: headless.then_some(Headless {
+                frames,
+                // filled in once the rom is looked up
+                timing: Timing::default(),
+                until_pc,
+                presses,
+            }),
+            screenshot,
+            tone,
+            error_policy,
+            stack_depth,
+            memory_mapped_stack,
+            timing,
+            speed,
+            config,
+            print_keymap,
+            no_database,
+        }
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    eprintln!("{USAGE}");
+    exit(1);
+}
@@ -0,0 +1,659 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::disasm::PROGRAM_START;
+
+// includes deeper than this are assumed to be including themselves
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Where in the source an error happened and what went wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// A source line with everything needed to report errors on it.
+struct SourceLine {
+    file: String,
+    line: usize,
+    text: String,
+}
+
+impl SourceLine {
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError {
+            file: self.file.clone(),
+            line: self.line,
+            message: message.into(),
+        }
+    }
+}
+
+/// A statement that emits bytes, waiting for labels to be resolved.
+struct Statement {
+    source: usize,
+    mnemonic: String,
+    operands: Vec<String>,
+}
+
+/// Assembles classic mnemonics into a rom loaded at 0x200.
+/// Includes are resolved relative to the current directory.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut lines = Vec::new();
+    read_lines(source, "<source>", Path::new("."), 0, &mut lines)?;
+    assemble_lines(&lines)
+}
+
+/// Assembles a source file. Includes are resolved relative to the including file.
+pub fn assemble_file(path: &Path) -> Result<Vec<u8>, AsmError> {
+    let mut lines = Vec::new();
+    include_file(path, None, 0, &mut lines)?;
+    assemble_lines(&lines)
+}
+
+fn include_file(
+    path: &Path,
+    included_from: Option<&SourceLine>,
+    depth: usize,
+    lines: &mut Vec<SourceLine>,
+) -> Result<(), AsmError> {
+    let error = |message: String| match included_from {
+        Some(line) => line.error(message),
+        None => AsmError {
+            file: path.display().to_string(),
+            line: 0,
+            message,
+        },
+    };
+
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(error("Includes are nested too deeply".to_string()));
+    }
+    let source = fs::read_to_string(path)
+        .map_err(|e| error(format!("Couldn't read {}: {e}", path.display())))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    read_lines(&source, &path.display().to_string(), dir, depth, lines)
+}
+
+/// Splits source into lines, expanding includes in place.
+fn read_lines(
+    source: &str,
+    file: &str,
+    dir: &Path,
+    depth: usize,
+    lines: &mut Vec<SourceLine>,
+) -> Result<(), AsmError> {
+    for (idx, text) in source.lines().enumerate() {
+        let line = SourceLine {
+            file: file.to_string(),
+            line: idx + 1,
+            text: strip_comment(text).trim().to_string(),
+        };
+
+        let mut words = line.text.splitn(2, char::is_whitespace);
+        if words
+            .next()
+            .is_some_and(|w| w.eq_ignore_ascii_case("include"))
+        {
+            let name = words.next().unwrap_or("").trim().trim_matches('"');
+            if name.is_empty() {
+                return Err(line.error("include needs a file name"));
+            }
+            let path: PathBuf = dir.join(name);
+            include_file(&path, Some(&line), depth + 1, lines)?;
+        } else {
+            lines.push(line);
+        }
+    }
+    Ok(())
+}
+
+fn strip_comment(text: &str) -> &str {
+    match text.find(';') {
+        Some(idx) => &text[..idx],
+        None => text,
+    }
+}
+
+fn assemble_lines(lines: &[SourceLine]) -> Result<Vec<u8>, AsmError> {
+    // first pass: find where every label ends up
+    let mut symbols = Symbols::default();
+    let mut statements = Vec::new();
+    let mut addr = PROGRAM_START;
+
+    for (idx, line) in lines.iter().enumerate() {
+        let mut text = line.text.as_str();
+
+        // any number of labels can come before a statement
+        while let Some((label, rest)) = split_label(text) {
+            symbols.define(label, Symbol::Value(addr as i64), line)?;
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = split_statement(text);
+
+        // NAME equ value
+        if let Some(value) = operands
+            .first()
+            .and_then(|first| first.strip_prefix_ignore_case("equ "))
+        {
+            symbols.define(
+                mnemonic,
+                Symbol::Expression(value.trim().to_string(), idx),
+                line,
+            )?;
+            continue;
+        }
+
+        let mnemonic = mnemonic.to_ascii_uppercase();
+        addr += statement_size(&mnemonic, &operands, line)?;
+        statements.push(Statement {
+            source: idx,
+            mnemonic,
+            operands,
+        });
+    }
+
+    // second pass: encode with every label known
+    let mut rom = Vec::new();
+    for statement in &statements {
+        let line = &lines[statement.source];
+        let ctx = Context {
+            symbols: &symbols,
+            lines,
+            line,
+        };
+        ctx.encode(&statement.mnemonic, &statement.operands, &mut rom)?;
+    }
+    Ok(rom)
+}
+
+/// Returns the label and the rest of the line for ```label: rest```.
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let (label, rest) = text.split_once(':')?;
+    let label = label.trim();
+    let is_identifier = !label.is_empty()
+        && !label.starts_with(|c: char| c.is_ascii_digit())
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    is_identifier.then_some((label, rest))
+}
+
+/// Splits ```MNEMONIC a, b, c``` into the mnemonic & operands.
+fn split_statement(text: &str) -> (&str, Vec<String>) {
+    let (mnemonic, rest) = match text.split_once(char::is_whitespace) {
+        Some((mnemonic, rest)) => (mnemonic, rest.trim()),
+        None => (text, ""),
+    };
+
+    // equ isn't comma separated, keep it whole
+    if rest
+        .get(..4)
+        .is_some_and(|s| s.eq_ignore_ascii_case("equ "))
+    {
+        return (mnemonic, vec![rest.to_string()]);
+    }
+
+    let operands = if rest.is_empty() {
+        Vec::new()
+    } else {
+        rest.split(',').map(|o| o.trim().to_string()).collect()
+    };
+    (mnemonic, operands)
+}
+
+fn statement_size(
+    mnemonic: &str,
+    operands: &[String],
+    line: &SourceLine,
+) -> Result<usize, AsmError> {
+    Ok(match mnemonic {
+        "DB" => operands.len(),
+        "DW" => operands.len() * 2,
+        "LD" if operands.len() == 2
+            && operands[0].eq_ignore_ascii_case("I")
+            && operands[1].strip_prefix_ignore_case("long ").is_some() =>
+        {
+            4
+        }
+        _ if is_mnemonic(mnemonic) => 2,
+        _ => return Err(line.error(format!("Unknown mnemonic {mnemonic}"))),
+    })
+}
+
+fn is_mnemonic(mnemonic: &str) -> bool {
+    matches!(
+        mnemonic,
+        "CLS"
+            | "RET"
+            | "SCD"
+            | "SCU"
+            | "SCR"
+            | "SCL"
+            | "EXIT"
+            | "LOW"
+            | "HIGH"
+            | "SYS"
+            | "JP"
+            | "CALL"
+            | "SE"
+            | "SNE"
+            | "SAVE"
+            | "LOAD"
+            | "LD"
+            | "ADD"
+            | "OR"
+            | "AND"
+            | "XOR"
+            | "SUB"
+            | "SHR"
+            | "SUBN"
+            | "SHL"
+            | "RND"
+            | "DRW"
+            | "SKP"
+            | "SKNP"
+            | "PLANE"
+            | "AUDIO"
+    )
+}
+
+enum Symbol {
+    Value(i64),
+    // constants can refer to labels further down, so they're evaluated when used
+    Expression(String, usize),
+}
+
+#[derive(Default)]
+struct Symbols {
+    symbols: HashMap<String, Symbol>,
+}
+
+impl Symbols {
+    fn define(&mut self, name: &str, symbol: Symbol, line: &SourceLine) -> Result<(), AsmError> {
+        if self.symbols.contains_key(name) {
+            return Err(line.error(format!("{name} is already defined")));
+        }
+        if is_reserved(name) {
+            return Err(line.error(format!("{name} is a reserved name")));
+        }
+        self.symbols.insert(name.to_string(), symbol);
+        Ok(())
+    }
+}
+
+fn is_reserved(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    parse_register(name).is_some()
+        || is_mnemonic(&upper)
+        || matches!(
+            upper.as_str(),
+            "I" | "DT" | "ST" | "K" | "F" | "HF" | "B" | "R" | "PITCH" | "LONG" | "DB" | "DW"
+        )
+}
+
+struct Context<'a> {
+    symbols: &'a Symbols,
+    lines: &'a [SourceLine],
+    line: &'a SourceLine,
+}
+
+impl Context<'_> {
+    fn encode(
+        &self,
+        mnemonic: &str,
+        operands: &[String],
+        rom: &mut Vec<u8>,
+    ) -> Result<(), AsmError> {
+        if mnemonic == "DB" {
+            for operand in operands {
+                rom.push(self.value(operand, 0xFF)? as u8);
+            }
+            return Ok(());
+        }
+        if mnemonic == "DW" {
+            for operand in operands {
+                rom.extend(self.value(operand, 0xFFFF)?.to_be_bytes());
+            }
+            return Ok(());
+        }
+
+        let ops: Vec<&str> = operands.iter().map(String::as_str).collect();
+        let upper: Vec<String> = ops.iter().map(|o| o.to_ascii_uppercase()).collect();
+        let upper: Vec<&str> = upper.iter().map(String::as_str).collect();
+
+        let opcode: u16 = match (mnemonic, upper.as_slice()) {
+            ("CLS", []) => 0x00E0,
+            ("RET", []) => 0x00EE,
+            ("SCD", [_]) => 0x00C0 | self.value(ops[0], 0xF)?,
+            ("SCU", [_]) => 0x00D0 | self.value(ops[0], 0xF)?,
+            ("SCR", []) => 0x00FB,
+            ("SCL", []) => 0x00FC,
+            ("EXIT", []) => 0x00FD,
+            ("LOW", []) => 0x00FE,
+            ("HIGH", []) => 0x00FF,
+            ("SYS", [_]) => self.value(ops[0], 0xFFF)?,
+            ("JP", ["V0", _]) => 0xB000 | self.value(ops[1], 0xFFF)?,
+            ("JP", [_]) => 0x1000 | self.value(ops[0], 0xFFF)?,
+            ("CALL", [_]) => 0x2000 | self.value(ops[0], 0xFFF)?,
+            ("SE", [x, y]) if is_register(x) && is_register(y) => self.xy(0x5000, x, y, 0)?,
+            ("SE", [x, _]) => self.xnn(0x3000, x, ops[1])?,
+            ("SNE", [x, y]) if is_register(x) && is_register(y) => self.xy(0x9000, x, y, 0)?,
+            ("SNE", [x, _]) => self.xnn(0x4000, x, ops[1])?,
+            ("SAVE", [x, y]) => self.xy(0x5000, x, y, 2)?,
+            ("LOAD", [x, y]) => self.xy(0x5000, x, y, 3)?,
+            ("LD", [x, y]) if is_register(x) && is_register(y) => self.xy(0x8000, x, y, 0)?,
+            ("LD", [x, "DT"]) => self.fx(x, 0x07)?,
+            ("LD", [x, "K"]) => self.fx(x, 0x0A)?,
+            ("LD", [x, "[I]"]) => self.fx(x, 0x65)?,
+            ("LD", [x, "R"]) => self.fx(x, 0x85)?,
+            ("LD", ["I", long]) if long.starts_with("LONG ") => {
+                let addr = self.value(ops[1][5..].trim(), 0xFFFF)?;
+                rom.extend(0xF000u16.to_be_bytes());
+                rom.extend(addr.to_be_bytes());
+                return Ok(());
+            }
+            ("LD", ["I", _]) => 0xA000 | self.value(ops[1], 0xFFF)?,
+            ("LD", ["DT", x]) => self.fx(x, 0x15)?,
+            ("LD", ["ST", x]) => self.fx(x, 0x18)?,
+            ("LD", ["F", x]) => self.fx(x, 0x29)?,
+            ("LD", ["HF", x]) => self.fx(x, 0x30)?,
+            ("LD", ["B", x]) => self.fx(x, 0x33)?,
+            ("LD", ["PITCH", x]) => self.fx(x, 0x3A)?,
+            ("LD", ["[I]", x]) => self.fx(x, 0x55)?,
+            ("LD", ["R", x]) => self.fx(x, 0x75)?,
+            ("LD", [x, _]) => self.xnn(0x6000, x, ops[1])?,
+            ("ADD", ["I", x]) => self.fx(x, 0x1E)?,
+            ("ADD", [x, y]) if is_register(x) && is_register(y) => self.xy(0x8000, x, y, 4)?,
+            ("ADD", [x, _]) => self.xnn(0x7000, x, ops[1])?,
+            ("OR", [x, y]) => self.xy(0x8000, x, y, 1)?,
+            ("AND", [x, y]) => self.xy(0x8000, x, y, 2)?,
+            ("XOR", [x, y]) => self.xy(0x8000, x, y, 3)?,
+            ("SUB", [x, y]) => self.xy(0x8000, x, y, 5)?,
+            // VY only matters with the shift quirk, default it to VX
+            ("SHR", [x]) => self.xy(0x8000, x, x, 6)?,
+            ("SHR", [x, y]) => self.xy(0x8000, x, y, 6)?,
+            ("SUBN", [x, y]) => self.xy(0x8000, x, y, 7)?,
+            ("SHL", [x]) => self.xy(0x8000, x, x, 0xE)?,
+            ("SHL", [x, y]) => self.xy(0x8000, x, y, 0xE)?,
+            ("RND", [x, _]) => self.xnn(0xC000, x, ops[1])?,
+            ("DRW", [x, y, _]) => self.xy(0xD000, x, y, self.value(ops[2], 0xF)?)?,
+            ("SKP", [x]) => self.register(x)? << 8 | 0xE09E,
+            ("SKNP", [x]) => self.register(x)? << 8 | 0xE0A1,
+            ("PLANE", [_]) => 0xF001 | self.value(ops[0], 0x3)? << 8,
+            ("AUDIO", []) => 0xF002,
+            _ => {
+                return Err(self.line.error(format!(
+                    "Invalid operands for {mnemonic}: {}",
+                    operands.join(", ")
+                )));
+            }
+        };
+
+        rom.extend(opcode.to_be_bytes());
+        Ok(())
+    }
+
+    fn register(&self, operand: &str) -> Result<u16, AsmError> {
+        parse_register(operand).map(|x| x as u16).ok_or_else(|| {
+            self.line
+                .error(format!("Expected a register, got {operand}"))
+        })
+    }
+
+    /// ```base``` with X & Y filled in, and ```n``` as the last nibble.
+    fn xy(&self, base: u16, x: &str, y: &str, n: u16) -> Result<u16, AsmError> {
+        Ok(base | self.register(x)? << 8 | self.register(y)? << 4 | n)
+    }
+
+    fn xnn(&self, base: u16, x: &str, nn: &str) -> Result<u16, AsmError> {
+        Ok(base | self.register(x)? << 8 | self.value(nn, 0xFF)?)
+    }
+
+    fn fx(&self, x: &str, nn: u16) -> Result<u16, AsmError> {
+        Ok(0xF000 | self.register(x)? << 8 | nn)
+    }
+
+    /// Evaluates an expression, checking it fits in ```max```.
+    /// Negative numbers are stored as two's complement.
+    fn value(&self, expression: &str, max: u16) -> Result<u16, AsmError> {
+        let value = self.evaluate(expression, &mut Vec::new())?;
+        let min = -(max as i64 / 2 + 1);
+        if value < min || value > max as i64 {
+            return Err(self
+                .line
+                .error(format!("{expression} = {value} doesn't fit in {max:#X}")));
+        }
+        Ok((value as u16) & max)
+    }
+
+    /// Adds & subtracts numbers, labels and constants.
+    /// ```evaluating``` holds the constants whose expressions are being worked out.
+    fn evaluate(&self, expression: &str, evaluating: &mut Vec<String>) -> Result<i64, AsmError> {
+        let mut total: i64 = 0;
+        let mut sign = 1;
+        let mut term = String::new();
+        for c in expression.chars().chain(std::iter::once('+')) {
+            match c {
+                '+' | '-' if !term.trim().is_empty() => {
+                    total = self
+                        .term(term.trim(), evaluating)?
+                        .checked_mul(sign)
+                        .and_then(|value| total.checked_add(value))
+                        .ok_or_else(|| self.line.error(format!("{expression} overflows")))?;
+                    term.clear();
+                    sign = if c == '-' { -1 } else { 1 };
+                }
+                // leading sign
+                '-' => sign = -sign,
+                '+' => {}
+                _ => term.push(c),
+            }
+        }
+        Ok(total)
+    }
+
+    fn term(&self, term: &str, evaluating: &mut Vec<String>) -> Result<i64, AsmError> {
+        if let Some(value) = parse_number(term) {
+            return Ok(value);
+        }
+
+        match self.symbols.symbols.get(term) {
+            Some(Symbol::Value(value)) => Ok(*value),
+            Some(Symbol::Expression(expression, source)) => {
+                if evaluating.iter().any(|name| name == term) {
+                    return Err(self.line.error(format!("{term} refers to itself")));
+                }
+                // report errors in the constant against the line it's defined on
+                let ctx = Context {
+                    symbols: self.symbols,
+                    lines: self.lines,
+                    line: &self.lines[*source],
+                };
+                evaluating.push(term.to_string());
+                let value = ctx.evaluate(expression, evaluating);
+                evaluating.pop();
+                value
+            }
+            None => Err(self.line.error(format!("Unknown symbol {term}"))),
+        }
+    }
+}
+
+fn is_register(operand: &str) -> bool {
+    parse_register(operand).is_some()
+}
+
+fn parse_register(operand: &str) -> Option<usize> {
+    let mut chars = operand.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('v' | 'V'), Some(c), None) => c.to_digit(16).map(|x| x as usize),
+        _ => None,
+    }
+}
+
+/// Decimal, 0x or $ hex, and 0b binary.
+fn parse_number(text: &str) -> Option<i64> {
+    let lower = text.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x").or_else(|| lower.strip_prefix('$')) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+trait StripPrefixIgnoreCase {
+    fn strip_prefix_ignore_case(&self, prefix: &str) -> Option<&str>;
+}
+
+impl StripPrefixIgnoreCase for str {
+    fn strip_prefix_ignore_case(&self, prefix: &str) -> Option<&str> {
+        let head = self.get(..prefix.len())?;
+        head.eq_ignore_ascii_case(prefix)
+            .then(|| &self[prefix.len()..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> AsmError {
+        assemble(source).expect_err("should fail to assemble")
+    }
+
+    #[test]
+    fn encodes_instructions() {
+        let source = "
+            CLS
+            ld v0, 0x05          ; mnemonics & registers in any case
+            LD VA, VB
+            DRW V1, V2, 5
+            SKP VF
+            SHR V3
+            LD I, long 0x1234
+            PLANE 3
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            [
+                0x00, 0xE0, 0x60, 0x05, 0x8A, 0xB0, 0xD1, 0x25, 0xEF, 0x9E, 0x83, 0x36, 0xF0, 0x00,
+                0x12, 0x34, 0xF3, 0x01
+            ]
+        );
+    }
+
+    #[test]
+    fn encodes_data_in_every_number_format() {
+        let source = "db 0x12, $FF, 0b0101, 10, -1\ndw 0x1234";
+        assert_eq!(
+            assemble(source).unwrap(),
+            [0x12, 0xFF, 0x05, 10, 0xFF, 0x12, 0x34]
+        );
+    }
+
+    #[test]
+    fn resolves_labels_and_constants() {
+        let source = "
+            main: JP end          ; forward reference
+            SPRITES equ data + 1  ; constants can use labels defined later
+            LD I, SPRITES + 2
+            LD V0, HEIGHT - 1
+            HEIGHT equ 5
+            data: db 1, 2, 3, 4
+            end: here: there: JP main ; several labels on one line
+            dw here, there
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            [
+                0x12, 0x0A, 0xA2, 0x09, 0x60, 0x04, 1, 2, 3, 4, 0x12, 0x00, 0x02, 0x0A, 0x02, 0x0A
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_equ_without_panicking_on_multibyte_characters() {
+        assert_eq!(error("DB aaaé").message, "Unknown symbol aaaé");
+        assert_eq!(assemble("X EQU 7\nDB X").unwrap(), [7]);
+    }
+
+    #[test]
+    fn reports_errors_with_their_line() {
+        let e = error("CLS\n\nFOO V0");
+        assert_eq!((e.line, e.message.as_str()), (3, "Unknown mnemonic FOO"));
+        assert_eq!(e.to_string(), "<source>:3: Unknown mnemonic FOO");
+    }
+
+    #[test]
+    fn rejects_bad_operands() {
+        assert_eq!(error("JP missing").message, "Unknown symbol missing");
+        assert_eq!(
+            error("LD V0, 0x100").message,
+            "0x100 = 256 doesn't fit in 0xFF"
+        );
+        assert_eq!(
+            error("DRW V0, V1").message,
+            "Invalid operands for DRW: V0, V1"
+        );
+        assert_eq!(error("SKP 5").message, "Expected a register, got 5");
+    }
+
+    #[test]
+    fn rejects_bad_symbols() {
+        assert_eq!(error("a: CLS\na: CLS").message, "a is already defined");
+        assert_eq!(error("v1 equ 5").message, "v1 is a reserved name");
+        assert_eq!(
+            error("X equ Y\nY equ X\nDB X").message,
+            "X refers to itself"
+        );
+        assert_eq!(error("X equ X + 1\nDB X").message, "X refers to itself");
+    }
+
+    #[test]
+    fn evaluates_long_chains_of_constants() {
+        let mut source = String::from("C0 equ 1\n");
+        for i in 1..=40 {
+            source += &format!("C{i} equ C{} + 1\n", i - 1);
+        }
+        source += "DB C40";
+        assert_eq!(assemble(&source).unwrap(), [41]);
+    }
+
+    #[test]
+    fn reports_overflow_instead_of_panicking() {
+        assert_eq!(
+            error("DB 0x7fffffffffffffff + 0x7fffffffffffffff").message,
+            "0x7fffffffffffffff + 0x7fffffffffffffff overflows"
+        );
+        assert!(
+            error("DB -0x7fffffffffffffff - 0x7fffffffffffffff - 2")
+                .message
+                .ends_with("overflows")
+        );
+    }
+
+    #[test]
+    fn reports_missing_includes_against_the_include_line() {
+        let e = error("CLS\ninclude \"does-not-exist.asm\"");
+        assert_eq!(e.line, 2);
+        assert!(e.message.starts_with("Couldn't read ./does-not-exist.asm"));
+    }
+}
@@ -0,0 +1,371 @@
+use chip8_core::audio::{AudioSink, DEFAULT_PATTERN, DEFAULT_PITCH};
+use kira::{
+    AudioManager, AudioManagerSettings, DefaultBackend, Frame,
+    info::Info,
+    sound::{Sound, SoundData},
+};
+use std::{
+    convert::Infallible,
+    f64::consts::TAU,
+    fmt,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering},
+    },
+};
+
+/// XO-CHIP plays the 128 sample pattern at 4000 samples a second at pitch 64.
+const PATTERN_SAMPLE_RATE: f64 = 4000.0;
+const PATTERN_BITS: f64 = 128.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Sine,
+    Triangle,
+}
+
+impl Waveform {
+    /// Sample at ```phase```, from 0 to 1 through one period.
+    fn sample(self, phase: f64) -> f64 {
+        match self {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        }
+    }
+}
+
+impl fmt::Display for Waveform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Waveform::Square => "square",
+            Waveform::Sine => "sine",
+            Waveform::Triangle => "triangle",
+        })
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "square" => Ok(Waveform::Square),
+            "sine" => Ok(Waveform::Sine),
+            "triangle" => Ok(Waveform::Triangle),
+            _ => Err(format!(
+                "Unknown waveform {s}, expected square, sine or triangle"
+            )),
+        }
+    }
+}
+
+/// The tone played while the sound timer is active.
+/// Roms that load their own XO-CHIP pattern play that instead, at the same volume.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    /// In Hz.
+    pub frequency: f64,
+    /// From 0 to 1.
+    pub volume: f32,
+    pub waveform: Waveform,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Self {
+            frequency: 440.0,
+            volume: 0.1,
+            waveform: Waveform::Square,
+        }
+    }
+}
+
+/// State shared with the audio thread. Atomics so the
+/// audio thread never has to wait on the emulator.
+struct PatternState {
+    // 16 byte pattern as two big endian halves
+    pattern: [AtomicU64; 2],
+    // false until a rom loads a pattern other than the default,
+    // the tone is synthesized until then
+    custom_pattern: AtomicBool,
+    pitch: AtomicU8,
+    playing: AtomicBool,
+}
+
+impl PatternState {
+    fn new() -> Self {
+        let state = Self {
+            pattern: [AtomicU64::new(0), AtomicU64::new(0)],
+            custom_pattern: AtomicBool::new(false),
+            pitch: AtomicU8::new(DEFAULT_PITCH),
+            playing: AtomicBool::new(false),
+        };
+        state.set_pattern(DEFAULT_PATTERN);
+        state
+    }
+
+    fn set_pattern(&self, pattern: [u8; 16]) {
+        self.custom_pattern
+            .store(pattern != DEFAULT_PATTERN, Ordering::Relaxed);
+        let (first, second) = pattern.split_at(8);
+        self.pattern[0].store(
+            u64::from_be_bytes(first.try_into().unwrap()),
+            Ordering::Relaxed,
+        );
+        self.pattern[1].store(
+            u64::from_be_bytes(second.try_into().unwrap()),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Bit ```idx``` of the pattern, most significant bit of the first byte first.
+    fn bit(&self, idx: usize) -> bool {
+        let half = self.pattern[idx / 64].load(Ordering::Relaxed);
+        (half >> (63 - idx % 64)) & 1 == 1
+    }
+}
+
+/// Plays a synthesized tone, or the XO-CHIP audio pattern buffer,
+/// through kira while the sound timer is active.
+pub struct Beep {
+    // dropping the manager stops all audio, so hold on to it
+    _manager: Option<AudioManager>,
+    state: Arc<PatternState>,
+}
+
+impl Beep {
+    pub fn new() -> Self {
+        Self::with_tone(Tone::default())
+    }
+
+    pub fn with_tone(tone: Tone) -> Self {
+        let state = Arc::new(PatternState::new());
+
+        let manager = match AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()) {
+            Ok(mut manager) => {
+                // the pattern sound runs for as long as the emulator does and
+                // outputs silence unless the beep is playing
+                let sound = PatternSoundData {
+                    state: state.clone(),
+                    tone,
+                };
+                if let Err(e) = manager.play(sound) {
+                    eprintln!("Could not play pattern: {e}");
+                }
+                Some(manager)
+            }
+            Err(e) => {
+                eprintln!("Could not create audio manager: {e}");
+                None
+            }
+        };
+
+        Self {
+            _manager: manager,
+            state,
+        }
+    }
+}
+
+impl AudioSink for Beep {
+    fn start_tone(&mut self) {
+        self.state.playing.store(true, Ordering::Relaxed);
+    }
+
+    fn stop_tone(&mut self) {
+        self.state.playing.store(false, Ordering::Relaxed);
+    }
+
+    fn set_pattern(&mut self, pattern: [u8; 16]) {
+        self.state.set_pattern(pattern);
+    }
+
+    fn set_pitch(&mut self, pitch: u8) {
+        self.state.pitch.store(pitch, Ordering::Relaxed);
+    }
+}
+
+impl Default for Beep {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct PatternSoundData {
+    state: Arc<PatternState>,
+    tone: Tone,
+}
+
+impl SoundData for PatternSoundData {
+    type Error = Infallible;
+    type Handle = ();
+
+    fn into_sound(self) -> Result<(Box<dyn Sound>, Self::Handle), Self::Error> {
+        Ok((
+            Box::new(PatternSound {
+                state: self.state,
+                tone: self.tone,
+                position: 0.0,
+                phase: 0.0,
+            }),
+            (),
+        ))
+    }
+}
+
+struct PatternSound {
+    state: Arc<PatternState>,
+    tone: Tone,
+    // position in the pattern, in pattern samples
+    position: f64,
+    // position in the synthesized tone's period, from 0 to 1
+    phase: f64,
+}
+
+/// Pattern samples a second at ```pitch```, doubling every 48 steps.
+fn pattern_sample_rate(pitch: u8) -> f64 {
+    PATTERN_SAMPLE_RATE * 2f64.powf((pitch as f64 - 64.0) / 48.0)
+}
+
+impl PatternSound {
+    fn next_pattern_sample(&mut self, dt: f64) -> f32 {
+        let rate = pattern_sample_rate(self.state.pitch.load(Ordering::Relaxed));
+
+        let sample = if self.state.bit(self.position as usize) {
+            1.0
+        } else {
+            -1.0
+        };
+        self.position = (self.position + rate * dt) % PATTERN_BITS;
+        sample
+    }
+
+    fn next_tone_sample(&mut self, dt: f64) -> f32 {
+        let sample = self.tone.waveform.sample(self.phase);
+        self.phase = (self.phase + self.tone.frequency * dt).fract();
+        sample as f32
+    }
+}
+
+impl Sound for PatternSound {
+    fn process(&mut self, out: &mut [Frame], dt: f64, _info: &Info) {
+        if !self.state.playing.load(Ordering::Relaxed) {
+            // restart from the beginning next time
+            self.position = 0.0;
+            self.phase = 0.0;
+            out.fill(Frame::ZERO);
+            return;
+        }
+
+        let custom_pattern = self.state.custom_pattern.load(Ordering::Relaxed);
+        for frame in out.iter_mut() {
+            let sample = if custom_pattern {
+                self.next_pattern_sample(dt)
+            } else {
+                self.next_tone_sample(dt)
+            };
+            *frame = Frame::from_mono(sample * self.tone.volume);
+        }
+    }
+
+    fn finished(&self) -> bool {
+        // the Beep owning the other reference has been dropped
+        Arc::strong_count(&self.state) == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sound(tone: Tone) -> PatternSound {
+        PatternSound {
+            state: Arc::new(PatternState::new()),
+            tone,
+            position: 0.0,
+            phase: 0.0,
+        }
+    }
+
+    #[test]
+    fn square_wave_is_high_for_the_first_half_of_each_period() {
+        assert_eq!(Waveform::Square.sample(0.0), 1.0);
+        assert_eq!(Waveform::Square.sample(0.49), 1.0);
+        assert_eq!(Waveform::Square.sample(0.5), -1.0);
+        assert_eq!(Waveform::Square.sample(0.99), -1.0);
+
+        // four samples a period
+        let mut sound = sound(Tone {
+            frequency: 1000.0,
+            ..Tone::default()
+        });
+        let samples: Vec<f32> = (0..8)
+            .map(|_| sound.next_tone_sample(1.0 / 4000.0))
+            .collect();
+        assert_eq!(samples, [1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, -1.0]);
+    }
+
+    #[test]
+    fn pattern_bits_are_read_most_significant_first() {
+        let state = PatternState::new();
+        let mut pattern = [0; 16];
+        pattern[0] = 0b1000_0001;
+        pattern[7] = 0b0000_0001;
+        pattern[8] = 0b1000_0000;
+        pattern[15] = 0b0000_0001;
+        state.set_pattern(pattern);
+
+        let set: Vec<usize> = (0..128).filter(|&idx| state.bit(idx)).collect();
+        assert_eq!(set, [0, 7, 63, 64, 127]);
+    }
+
+    #[test]
+    fn only_patterns_other_than_the_default_replace_the_tone() {
+        let state = PatternState::new();
+        assert!(!state.custom_pattern.load(Ordering::Relaxed));
+        state.set_pattern([0xFF; 16]);
+        assert!(state.custom_pattern.load(Ordering::Relaxed));
+        state.set_pattern(DEFAULT_PATTERN);
+        assert!(!state.custom_pattern.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn pitch_doubles_the_rate_every_48_steps() {
+        assert_eq!(pattern_sample_rate(64), 4000.0);
+        assert_eq!(pattern_sample_rate(112), 8000.0);
+        assert_eq!(pattern_sample_rate(16), 2000.0);
+        assert!((pattern_sample_rate(255) - 4000.0 * 2f64.powf(191.0 / 48.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pattern_plays_one_bit_a_sample_at_the_default_pitch_and_loops() {
+        let mut sound = sound(Tone::default());
+        let mut pattern = [0; 16];
+        pattern[0] = 0b1010_0000;
+        pattern[15] = 0b0000_0001;
+        sound.state.set_pattern(pattern);
+
+        let dt = 1.0 / PATTERN_SAMPLE_RATE;
+        let samples: Vec<f32> = (0..130).map(|_| sound.next_pattern_sample(dt)).collect();
+        assert_eq!(samples[..4], [1.0, -1.0, 1.0, -1.0]);
+        assert_eq!(samples[127], 1.0);
+        // back to the start
+        assert_eq!(samples[128..], [1.0, -1.0]);
+
+        // an octave up skips every other bit
+        sound.state.pitch.store(112, Ordering::Relaxed);
+        sound.position = 0.0;
+        let samples: Vec<f32> = (0..2).map(|_| sound.next_pattern_sample(dt)).collect();
+        assert_eq!(samples, [1.0, 1.0]);
+    }
+}
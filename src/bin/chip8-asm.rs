@@ -0,0 +1,48 @@
+use chip8::asm::assemble_file;
+use std::{env, fs, path::PathBuf, process::exit};
+
+const USAGE: &str = "Usage: chip8-asm <source> [-o <output.ch8>]";
+
+fn main() {
+    let mut source_path = None;
+    let mut output_path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" | "-o" => {
+                let value = args
+                    .next()
+                    .unwrap_or_else(|| fail("--output needs a value"));
+                output_path = Some(PathBuf::from(value));
+            }
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                exit(0);
+            }
+            _ if arg.starts_with('-') => fail(&format!("Unknown option {arg}")),
+            _ => source_path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let Some(source_path) = source_path else {
+        fail("Supply a path to the source you would like to assemble.");
+    };
+    let output_path = output_path.unwrap_or_else(|| source_path.with_extension("ch8"));
+
+    let rom = assemble_file(&source_path).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(1);
+    });
+    fs::write(&output_path, &rom).unwrap_or_else(|e| {
+        eprintln!("Couldn't write {}: {e}", output_path.display());
+        exit(1);
+    });
+    println!("Wrote {} bytes to {}", rom.len(), output_path.display());
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    eprintln!("{USAGE}");
+    exit(1);
+}
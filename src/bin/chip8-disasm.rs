@@ -0,0 +1,43 @@
+use chip8::disasm::{Syntax, disassemble};
+use std::{env, fs, process::exit};
+
+const USAGE: &str = "Usage: chip8-disasm [--syntax <classic|octo>] <path_to_rom>";
+
+fn main() {
+    let mut rom_path = None;
+    let mut syntax = Syntax::default();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--syntax" | "-s" => {
+                let value = args
+                    .next()
+                    .unwrap_or_else(|| fail("--syntax needs a value"));
+                syntax = value.parse().unwrap_or_else(|e: String| fail(&e));
+            }
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                exit(0);
+            }
+            _ if arg.starts_with('-') => fail(&format!("Unknown option {arg}")),
+            _ => rom_path = Some(arg),
+        }
+    }
+
+    let Some(rom_path) = rom_path else {
+        fail("Supply a path to the rom you would like to disassemble.");
+    };
+
+    let rom = fs::read(&rom_path).unwrap_or_else(|e| {
+        eprintln!("Couldn't read rom: {e}");
+        exit(1);
+    });
+    print!("{}", disassemble(&rom, syntax));
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    eprintln!("{USAGE}");
+    exit(1);
+}
@@ -0,0 +1,230 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    path::Path,
+    path::PathBuf,
+};
+
+use serde::Deserialize;
+
+use crate::keymap::{Binding, GAMEPAD_HINTS, KEYBOARD_HINTS, KeyBindings, Keymap};
+
+/// Settings read from a TOML file, or JSON when the file ends in ```.json```.
+///
+/// ```toml
+/// [keys]
+/// 5 = ["W", "Up"]
+///
+/// [buttons]
+/// 5 = ["South", "DpadUp"]
+///
+/// # only for the rom with this SHA-1
+/// [roms.0123456789abcdef0123456789abcdef01234567.keys]
+/// 4 = "Left"
+/// 6 = "Right"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Replace the default bindings for every rom.
+    pub keys: KeyBindings,
+    /// Replace the default gamepad bindings for every rom.
+    pub buttons: KeyBindings,
+    /// Overrides for single roms, keyed by the rom's SHA-1 in hex.
+    pub roms: HashMap<String, RomConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RomConfig {
+    pub keys: KeyBindings,
+    pub buttons: KeyBindings,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+        let config = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&text).map_err(|e| e.to_string())
+        };
+        config.map_err(|e| format!("Invalid config {}: {e}", path.display()))
+    }
+
+    /// ```$XDG_CONFIG_HOME/chip8/config.toml```, falling back to ```~/.config```.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(dir.join("chip8").join("config.toml"))
+    }
+
+    /// The default keyboard layout with the global bindings, then the rom
+    /// database's ```hints``` for keys the global bindings don't use, then
+    /// the rom's own bindings on top.
+    pub fn keymap(
+        &self,
+        rom_hash: &[u8; 20],
+        hints: &BTreeMap<String, u8>,
+    ) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+        keymap.apply(&self.keys)?;
+        keymap.apply_hints(hints, &unbound(&KEYBOARD_HINTS, &self.keys));
+        if let Some(rom) = self.rom(rom_hash) {
+            keymap.apply(&rom.keys)?;
+        }
+        Ok(keymap)
+    }
+
+    /// Same as ```keymap```, for gamepad buttons.
+    pub fn buttonmap(
+        &self,
+        rom_hash: &[u8; 20],
+        hints: &BTreeMap<String, u8>,
+    ) -> Result<Keymap, String> {
+        let mut buttonmap = Keymap::gamepad();
+        buttonmap.apply(&self.buttons)?;
+        buttonmap.apply_hints(hints, &unbound(&GAMEPAD_HINTS, &self.buttons));
+        if let Some(rom) = self.rom(rom_hash) {
+            buttonmap.apply(&rom.buttons)?;
+        }
+        Ok(buttonmap)
+    }
+
+    fn rom(&self, rom_hash: &[u8; 20]) -> Option<&RomConfig> {
+        let hash = hex(rom_hash);
+        self.roms
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(&hash))
+            .map(|(_, rom)| rom)
+    }
+}
+
+/// The entries of ```names``` whose key isn't in ```bindings```, so hints
+/// don't move keys the user placed by hand.
+fn unbound<'a>(names: &[(&'a str, &'a str)], bindings: &KeyBindings) -> Vec<(&'a str, &'a str)> {
+    names
+        .iter()
+        .filter(|(_, name)| {
+            !bindings
+                .values()
+                .flat_map(Binding::names)
+                .any(|bound| bound.eq_ignore_ascii_case(name))
+        })
+        .copied()
+        .collect()
+}
+
+/// Lowercase hex, the way ```sha1sum``` prints hashes.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: [u8; 20] = [0xAB; 20];
+
+    fn config(toml: &str) -> Config {
+        toml::from_str(toml).unwrap()
+    }
+
+    /// Writes ```text``` to a file named ```name``` that's removed on drop.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, text: &str) -> Self {
+            let path = env::temp_dir().join(format!("chip8-{}-{name}", std::process::id()));
+            fs::write(&path, text).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn rom_bindings_win_over_global_ones_which_win_over_hints() {
+        let config = config(&format!(
+            "
+            [keys]
+            4 = [\"Q\", \"Left\"]
+            5 = \"Up\"
+
+            [roms.{}.keys]
+            6 = \"L\"
+            ",
+            hex(&HASH).to_uppercase()
+        ));
+        let hints = BTreeMap::from([
+            ("up".to_string(), 2),
+            ("down".to_string(), 8),
+            ("right".to_string(), 6),
+        ]);
+        let keymap = config.keymap(&HASH, &hints).unwrap();
+
+        assert_eq!(keymap.bindings(0x1), ["1"], "defaults");
+        assert_eq!(keymap.bindings(0x4), ["Q", "Left"], "global");
+        assert_eq!(keymap.bindings(0x5), ["Up"], "global, not moved by a hint");
+        assert_eq!(keymap.bindings(0x2), ["2"]);
+        assert_eq!(keymap.bindings(0x8), ["S", "Down"], "hint");
+        assert_eq!(keymap.bindings(0x6), ["L"], "rom");
+
+        let other = config.keymap(&[0; 20], &hints).unwrap();
+        assert_eq!(
+            other.bindings(0x6),
+            ["E", "Right"],
+            "other roms keep the hints"
+        );
+    }
+
+    #[test]
+    fn buttons_have_their_own_bindings() {
+        let config = config("[buttons]\n5 = \"North\"");
+        let hints = BTreeMap::from([("a".to_string(), 0xA)]);
+        let buttonmap = config.buttonmap(&HASH, &hints).unwrap();
+        assert_eq!(buttonmap.bindings(0x5), ["North"]);
+        assert_eq!(buttonmap.bindings(0xA), ["South"]);
+        assert_eq!(buttonmap.bindings(0x2), ["DpadUp"]);
+    }
+
+    #[test]
+    fn rejects_invalid_keypad_indices_in_rom_bindings() {
+        let config = config(&format!("[roms.{}.keys]\nX = \"Up\"", hex(&HASH)));
+        let e = config.keymap(&HASH, &BTreeMap::new()).unwrap_err();
+        assert_eq!(e, "Invalid Chip-8 key X, expected 0 to F");
+    }
+
+    #[test]
+    fn loads_toml_or_json_by_extension() {
+        let toml = TempFile::new("config.toml", "[keys]\n5 = [\"W\", \"Up\"]");
+        let json = TempFile::new("config.json", r#"{"keys": {"5": ["W", "Up"]}}"#);
+        for file in [&toml, &json] {
+            let keymap = Config::load(&file.0)
+                .unwrap()
+                .keymap(&HASH, &BTreeMap::new())
+                .unwrap();
+            assert_eq!(keymap.bindings(0x5), ["W", "Up"]);
+        }
+
+        // JSON isn't valid TOML and the other way round
+        let swapped = TempFile::new("swapped.toml", r#"{"keys": {"5": "W"}}"#);
+        assert!(Config::load(&swapped.0).is_err());
+        let swapped = TempFile::new("swapped.json", "[keys]\n5 = \"W\"");
+        assert!(Config::load(&swapped.0).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        let file = TempFile::new("unknown.toml", "[keyz]\n5 = \"W\"");
+        let e = Config::load(&file.0).unwrap_err();
+        assert!(e.starts_with("Invalid config"), "{e}");
+        assert!(e.contains("keyz"), "{e}");
+    }
+}
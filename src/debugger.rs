@@ -0,0 +1,415 @@
+use std::io::{self, BufRead, Write};
+
+use chip8::disasm::{Syntax, disassemble_at};
+use chip8_core::{
+    emulator::Chip8,
+    headless::is_halted,
+    scheduler::{Scheduler, Timing},
+};
+
+/// How far ```continue``` runs without hitting anything, hours at any usual speed.
+const MAX_CONTINUE_INSTRUCTIONS: usize = 10_000_000;
+
+/// Opcode pattern like ```Dxy5``` or ```8xy6```.
+/// Hex digits have to match, anything else matches any nibble.
+struct OpcodePattern {
+    mask: u16,
+    value: u16,
+    text: String,
+}
+
+impl OpcodePattern {
+    fn parse(text: &str) -> Option<Self> {
+        if text.len() != 4 || !text.is_ascii() {
+            return None;
+        }
+
+        let mut mask = 0;
+        let mut value = 0;
+        for c in text.chars() {
+            mask <<= 4;
+            value <<= 4;
+            if let Some(nibble) = c.to_digit(16) {
+                mask |= 0xF;
+                value |= nibble as u16;
+            }
+        }
+
+        Some(Self {
+            mask,
+            value,
+            text: text.to_string(),
+        })
+    }
+
+    fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.value
+    }
+}
+
+enum Breakpoint {
+    Address(usize),
+    Opcode(OpcodePattern),
+}
+
+#[derive(Clone, Copy)]
+enum Watchpoint {
+    Register(usize),
+    Index,
+    Memory(usize),
+}
+
+impl Watchpoint {
+    fn read(self, em: &Chip8) -> u16 {
+        match self {
+            Watchpoint::Register(x) => em.v()[x] as u16,
+            Watchpoint::Index => em.i(),
+            Watchpoint::Memory(addr) => em.memory()[addr] as u16,
+        }
+    }
+
+    fn describe(self) -> String {
+        match self {
+            Watchpoint::Register(x) => format!("V{x:X}"),
+            Watchpoint::Index => "I".to_string(),
+            Watchpoint::Memory(addr) => format!("[{addr:#05X}]"),
+        }
+    }
+}
+
+/// Terminal REPL for stepping through a rom. Doesn't need a window or GPU.
+pub struct Debugger {
+    em: Chip8,
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    // ticks the timers between instructions
+    scheduler: Scheduler,
+}
+
+impl Debugger {
+    pub fn new(em: Chip8, timing: Timing) -> Self {
+        Self {
+            em,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            scheduler: Scheduler::new(timing),
+        }
+    }
+
+    pub fn repl(&mut self) {
+        println!("Chip-8 debugger. Type 'help' for a list of commands.");
+        self.print_location();
+
+        let stdin = io::stdin();
+        let mut last_command = String::new();
+        loop {
+            print!("(chip8) ");
+            io::stdout().flush().unwrap();
+
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+                // EOF
+                break;
+            }
+
+            // an empty line repeats the last command, like gdb
+            let line = line.trim();
+            let command = if line.is_empty() {
+                last_command.clone()
+            } else {
+                line.to_string()
+            };
+            last_command = command.clone();
+
+            let mut parts = command.split_whitespace();
+            let Some(name) = parts.next() else {
+                continue;
+            };
+            let args: Vec<&str> = parts.collect();
+
+            match name {
+                "s" | "step" => {
+                    let count = args.first().and_then(|n| n.parse().ok()).unwrap_or(1);
+                    self.step(count);
+                }
+                "c" | "continue" => self.continue_execution(),
+                "b" | "break" => self.add_breakpoint(&args),
+                "w" | "watch" => self.add_watchpoint(&args),
+                "d" | "delete" => self.delete(&args),
+                "l" | "list" => self.list(),
+                "r" | "regs" => self.print_registers(),
+                "stack" => self.print_stack(),
+                "t" | "timers" => self.print_timers(),
+                "x" | "mem" => self.hexdump(&args),
+                "h" | "help" => print_help(),
+                "q" | "quit" => break,
+                _ => println!("Unknown command {name:?}. Type 'help' for a list of commands."),
+            }
+        }
+    }
+
+    /// Runs one instruction, ticking the timers at the same rate as the main loop.
+    /// Returns false if the instruction failed.
+    fn execute_one(&mut self) -> bool {
+        let result = self.scheduler.step(&mut self.em, 0);
+
+        // errors skipped under --on-error log
+        for e in self.em.take_logged_errors() {
+            println!("Error: {e}");
+        }
+        if let Err(e) = result {
+            println!("Error: {e}");
+            return false;
+        }
+        true
+    }
+
+    /// Runs one instruction and reports any watchpoints it tripped or errors it hit.
+    fn execute_watched(&mut self) -> bool {
+        let before: Vec<u16> = self.watchpoints.iter().map(|w| w.read(&self.em)).collect();
+        let mut hit = !self.execute_one();
+
+        for (watch, old) in self.watchpoints.iter().zip(before) {
+            let new = watch.read(&self.em);
+            if new != old {
+                println!("Watchpoint {}: {old:#X} -> {new:#X}", watch.describe());
+                hit = true;
+            }
+        }
+        hit
+    }
+
+    fn step(&mut self, count: usize) {
+        for _ in 0..count {
+            if self.em.has_exited() {
+                println!("Program has exited.");
+                break;
+            }
+            if self.em.has_halted() {
+                println!("Program has halted.");
+                break;
+            }
+            if self.execute_watched() {
+                break;
+            }
+        }
+        self.print_location();
+    }
+
+    fn continue_execution(&mut self) {
+        // always move off the current instruction, even if it has a breakpoint
+        let mut stopped = self.execute_watched();
+        let mut count = 1;
+        while !stopped && !self.em.has_exited() && !self.em.has_halted() {
+            if let Some(idx) = self.breakpoint_hit() {
+                println!("Breakpoint {idx} hit.");
+                break;
+            }
+            if is_halted(&self.em) {
+                println!("Program is jumping to itself.");
+                break;
+            }
+            if count == MAX_CONTINUE_INSTRUCTIONS {
+                println!("Stopped after {count} instructions without hitting anything.");
+                break;
+            }
+            stopped = self.execute_watched();
+            count += 1;
+        }
+
+        if self.em.has_exited() {
+            println!("Program has exited.");
+        }
+        self.print_location();
+    }
+
+    fn breakpoint_hit(&self) -> Option<usize> {
+        let pc = self.em.pc();
+        let opcode = self.em.peek_opcode();
+        self.breakpoints.iter().position(|b| match b {
+            Breakpoint::Address(addr) => *addr == pc,
+            Breakpoint::Opcode(pattern) => pattern.matches(opcode),
+        })
+    }
+
+    fn add_breakpoint(&mut self, args: &[&str]) {
+        let breakpoint = match args {
+            ["op", pattern] => match OpcodePattern::parse(pattern) {
+                Some(pattern) => Breakpoint::Opcode(pattern),
+                None => {
+                    println!("Opcode patterns are 4 characters, e.g. Dxy5 or 00E0.");
+                    return;
+                }
+            },
+            [addr] => match parse_number(addr) {
+                Some(addr) => Breakpoint::Address(addr),
+                None => {
+                    println!("Couldn't parse address {addr:?}.");
+                    return;
+                }
+            },
+            _ => {
+                println!("Usage: break <addr> | break op <pattern>");
+                return;
+            }
+        };
+
+        self.breakpoints.push(breakpoint);
+        println!("Breakpoint {} set.", self.breakpoints.len() - 1);
+    }
+
+    fn add_watchpoint(&mut self, args: &[&str]) {
+        let [target] = args else {
+            println!("Usage: watch <v0-vf | i | addr>");
+            return;
+        };
+
+        let target = target.to_ascii_lowercase();
+        let watchpoint = if target == "i" {
+            Watchpoint::Index
+        } else if let Some(x) = target
+            .strip_prefix('v')
+            .and_then(|x| usize::from_str_radix(x, 16).ok())
+            .filter(|x| *x < 16)
+        {
+            Watchpoint::Register(x)
+        } else if let Some(addr) = parse_number(&target).filter(|a| *a < self.em.memory().len()) {
+            Watchpoint::Memory(addr)
+        } else {
+            println!("Can't watch {target:?}.");
+            return;
+        };
+
+        self.watchpoints.push(watchpoint);
+        println!(
+            "Watchpoint {} set on {}.",
+            self.watchpoints.len() - 1,
+            watchpoint.describe()
+        );
+    }
+
+    fn delete(&mut self, args: &[&str]) {
+        match args {
+            ["break", idx] | [idx] => match idx.parse::<usize>() {
+                Ok(idx) if idx < self.breakpoints.len() => {
+                    self.breakpoints.remove(idx);
+                }
+                _ => println!("No breakpoint {idx}."),
+            },
+            ["watch", idx] => match idx.parse::<usize>() {
+                Ok(idx) if idx < self.watchpoints.len() => {
+                    self.watchpoints.remove(idx);
+                }
+                _ => println!("No watchpoint {idx}."),
+            },
+            _ => println!("Usage: delete [break|watch] <n>"),
+        }
+    }
+
+    fn list(&self) {
+        for (idx, breakpoint) in self.breakpoints.iter().enumerate() {
+            match breakpoint {
+                Breakpoint::Address(addr) => println!("break {idx}: pc == {addr:#05X}"),
+                Breakpoint::Opcode(pattern) => println!("break {idx}: opcode {}", pattern.text),
+            }
+        }
+        for (idx, watch) in self.watchpoints.iter().enumerate() {
+            println!("watch {idx}: {}", watch.describe());
+        }
+    }
+
+    fn print_location(&self) {
+        println!(
+            "{:#05X}: {:04X}  {}",
+            self.em.pc(),
+            self.em.peek_opcode(),
+            disassemble_at(self.em.memory(), self.em.pc(), Syntax::Classic)
+        );
+    }
+
+    fn print_registers(&self) {
+        for (x, value) in self.em.v().iter().enumerate() {
+            print!("V{x:X}={value:02X} ");
+            if x % 8 == 7 {
+                println!();
+            }
+        }
+        println!("I={:#06X} PC={:#06X}", self.em.i(), self.em.pc());
+    }
+
+    fn print_stack(&self) {
+        let stack = self.em.stack();
+        if stack.is_empty() {
+            println!("Stack is empty.");
+        }
+        // most recent call first
+        for (depth, addr) in stack.iter().rev().enumerate() {
+            println!("#{depth} {addr:#05X}");
+        }
+    }
+
+    fn print_timers(&self) {
+        println!(
+            "delay={} sound={}",
+            self.em.delay_timer(),
+            self.em.sound_timer()
+        );
+    }
+
+    /// Prints memory 16 bytes per row. Defaults to the area around I.
+    fn hexdump(&self, args: &[&str]) {
+        let memory = self.em.memory();
+        let start = match args.first() {
+            Some(addr) => match parse_number(addr) {
+                Some(addr) => addr,
+                None => {
+                    println!("Couldn't parse address {addr:?}.");
+                    return;
+                }
+            },
+            None => (self.em.i() as usize).saturating_sub(16),
+        };
+        let len = args.get(1).and_then(|n| parse_number(n)).unwrap_or(48);
+
+        let start = start.min(memory.len()) & !0xF;
+        let end = start.saturating_add(len).min(memory.len());
+        for row_start in (start..end).step_by(16) {
+            let row = &memory[row_start..(row_start + 16).min(end)];
+            let hex: Vec<String> = row.iter().map(|b| format!("{b:02X}")).collect();
+            let marker = if (row_start..row_start + 16).contains(&(self.em.i() as usize)) {
+                "<- I"
+            } else {
+                ""
+            };
+            println!("{row_start:04X}: {} {marker}", hex.join(" "));
+        }
+    }
+}
+
+/// Parses decimal or 0x prefixed hex.
+fn parse_number(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn print_help() {
+    println!(
+        "\
+step [n]            (s) run n instructions, default 1
+continue            (c) run until a breakpoint or watchpoint, or the program
+                        jumps to itself
+break <addr>        (b) stop when pc reaches addr
+break op <pattern>  stop before an opcode matching pattern, e.g. Dxy5
+watch <v0-vf|i|addr> (w) stop when a register or memory address changes
+delete [break|watch] <n> (d) remove a breakpoint or watchpoint
+list                (l) show breakpoints and watchpoints
+regs                (r) print V registers, I and PC
+stack               print the call stack
+timers              (t) print the delay and sound timers
+mem [addr] [len]    (x) hexdump memory, defaults to around I
+quit                (q) exit
+An empty line repeats the last command."
+    );
+}
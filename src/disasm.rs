@@ -0,0 +1,462 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+    str::FromStr,
+};
+
+use chip8_core::instruction::Instruction;
+
+/// Roms are loaded here, so this is where disassembly starts.
+pub const PROGRAM_START: usize = 0x200;
+// data runs are split into lines of at most this many bytes
+const BYTES_PER_DATA_LINE: usize = 8;
+
+/// Mnemonic style for the output. Classic is the default since it's what
+/// ```chip8-asm``` reads back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// Octo assembly, e.g. ```v0 := 0x05```.
+    Octo,
+    /// Cowgod style mnemonics, e.g. ```LD V0, 0x05```.
+    #[default]
+    Classic,
+}
+
+impl FromStr for Syntax {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "octo" => Ok(Syntax::Octo),
+            "classic" | "cowgod" => Ok(Syntax::Classic),
+            _ => Err(format!("Unknown syntax {s:?}. Expected octo or classic")),
+        }
+    }
+}
+
+enum Line {
+    Code {
+        addr: usize,
+        instruction: Instruction,
+        opcode: u16,
+        // the address following F000
+        long_address: u16,
+    },
+    Data {
+        addr: usize,
+        bytes: Vec<u8>,
+    },
+}
+
+/// Addresses found by following every path the program can take from 0x200.
+struct Reachability {
+    instructions: BTreeSet<usize>,
+    // jump & call destinations
+    code_targets: BTreeSet<usize>,
+    // addresses loaded into i
+    data_targets: BTreeSet<usize>,
+}
+
+/// Disassembles a rom as if it were loaded at 0x200.
+/// Bytes that can't be reached from the entry point are output as data.
+pub fn disassemble(rom: &[u8], syntax: Syntax) -> String {
+    let reachable = find_reachable(rom);
+    let lines = split_lines(rom, &reachable);
+
+    // only addresses that start a line can be labelled
+    let line_starts: BTreeSet<usize> = lines
+        .iter()
+        .map(|line| match line {
+            Line::Code { addr, .. } | Line::Data { addr, .. } => *addr,
+        })
+        .collect();
+    let mut labels: HashMap<usize, String> = reachable
+        .code_targets
+        .iter()
+        .chain(&reachable.data_targets)
+        .filter(|addr| line_starts.contains(addr))
+        .map(|addr| (*addr, format!("L{addr:03X}")))
+        .collect();
+    labels.insert(PROGRAM_START, "main".to_string());
+
+    let address = |addr: u16| match labels.get(&(addr as usize)) {
+        Some(label) => label.clone(),
+        None => format!("0x{addr:03X}"),
+    };
+
+    let mut out = String::new();
+    for line in &lines {
+        let addr = match line {
+            Line::Code { addr, .. } | Line::Data { addr, .. } => *addr,
+        };
+        if let Some(label) = labels.get(&addr) {
+            match syntax {
+                Syntax::Octo => writeln!(out, ": {label}").unwrap(),
+                Syntax::Classic => writeln!(out, "{label}:").unwrap(),
+            }
+        }
+
+        let comment = match syntax {
+            Syntax::Octo => '#',
+            Syntax::Classic => ';',
+        };
+        match line {
+            Line::Code {
+                addr,
+                instruction,
+                opcode,
+                long_address,
+            } => {
+                let text = format_instruction(*instruction, *long_address, syntax, &address);
+                writeln!(out, "\t{text:<27} {comment} {addr:03X}: {opcode:04X}").unwrap();
+            }
+            Line::Data { addr, bytes } => {
+                let text = format_data(bytes, syntax);
+                writeln!(out, "\t{text:<27} {comment} {addr:03X}").unwrap();
+            }
+        }
+    }
+    out
+}
+
+/// Disassembles the single instruction at ```addr```, without labels.
+pub fn disassemble_at(memory: &[u8], addr: usize, syntax: Syntax) -> String {
+    let word = |addr: usize| {
+        let first_byte = memory.get(addr).copied().unwrap_or(0);
+        let second_byte = memory.get(addr + 1).copied().unwrap_or(0);
+        u16::from_be_bytes([first_byte, second_byte])
+    };
+    let address = |addr: u16| format!("0x{addr:03X}");
+    format_instruction(
+        Instruction::decode(word(addr)),
+        word(addr + 2),
+        syntax,
+        &address,
+    )
+}
+
+fn find_reachable(rom: &[u8]) -> Reachability {
+    let end = PROGRAM_START + rom.len();
+    let word = |addr: usize| {
+        let offset = addr - PROGRAM_START;
+        u16::from_be_bytes([rom[offset], rom[offset + 1]])
+    };
+
+    let mut reachable = Reachability {
+        instructions: BTreeSet::new(),
+        code_targets: BTreeSet::new(),
+        data_targets: BTreeSet::new(),
+    };
+    let mut pending = vec![PROGRAM_START];
+
+    while let Some(addr) = pending.pop() {
+        if addr < PROGRAM_START || addr + 2 > end || reachable.instructions.contains(&addr) {
+            continue;
+        }
+
+        let instruction = Instruction::decode(word(addr));
+        let next = addr + instruction.size();
+        if next > end || matches!(instruction, Instruction::Unknown { .. }) {
+            // ran into data, or off the end of the rom
+            continue;
+        }
+        reachable.instructions.insert(addr);
+
+        match instruction {
+            Instruction::Jump { nnn } => {
+                reachable.code_targets.insert(nnn as usize);
+                pending.push(nnn as usize);
+            }
+            Instruction::Call { nnn } => {
+                reachable.code_targets.insert(nnn as usize);
+                pending.push(nnn as usize);
+                pending.push(next);
+            }
+            // the offset isn't known, but jump tables usually start at nnn
+            Instruction::JumpWithOffset { nnn, .. } => {
+                reachable.code_targets.insert(nnn as usize);
+                pending.push(nnn as usize);
+            }
+            Instruction::Return | Instruction::Exit => {}
+            Instruction::SetIndex { nnn } => {
+                reachable.data_targets.insert(nnn as usize);
+                pending.push(next);
+            }
+            Instruction::LoadLongIndex => {
+                reachable.data_targets.insert(word(addr + 2) as usize);
+                pending.push(next);
+            }
+            _ if instruction.is_skip() => {
+                pending.push(next);
+                let skipped_size = if next + 2 <= end {
+                    Instruction::decode(word(next)).size()
+                } else {
+                    2
+                };
+                pending.push(next + skipped_size);
+            }
+            _ => pending.push(next),
+        }
+    }
+
+    reachable
+}
+
+fn split_lines(rom: &[u8], reachable: &Reachability) -> Vec<Line> {
+    let end = PROGRAM_START + rom.len();
+    let byte = |addr: usize| rom[addr - PROGRAM_START];
+    let is_target = |addr: usize| {
+        reachable.code_targets.contains(&addr) || reachable.data_targets.contains(&addr)
+    };
+
+    let mut lines = Vec::new();
+    let mut addr = PROGRAM_START;
+    while addr < end {
+        if reachable.instructions.contains(&addr) {
+            let opcode = u16::from_be_bytes([byte(addr), byte(addr + 1)]);
+            let instruction = Instruction::decode(opcode);
+            let long_address = match instruction {
+                Instruction::LoadLongIndex => u16::from_be_bytes([byte(addr + 2), byte(addr + 3)]),
+                _ => 0,
+            };
+            lines.push(Line::Code {
+                addr,
+                instruction,
+                opcode,
+                long_address,
+            });
+            addr += instruction.size();
+            continue;
+        }
+
+        let start = addr;
+        let mut bytes = Vec::new();
+        loop {
+            bytes.push(byte(addr));
+            addr += 1;
+            if addr >= end
+                || reachable.instructions.contains(&addr)
+                || is_target(addr)
+                || bytes.len() == BYTES_PER_DATA_LINE
+            {
+                break;
+            }
+        }
+        lines.push(Line::Data { addr: start, bytes });
+    }
+    lines
+}
+
+fn format_data(bytes: &[u8], syntax: Syntax) -> String {
+    let bytes: Vec<String> = bytes.iter().map(|b| format!("0x{b:02X}")).collect();
+    match syntax {
+        Syntax::Octo => bytes.join(" "),
+        Syntax::Classic => format!("db {}", bytes.join(", ")),
+    }
+}
+
+fn format_instruction(
+    instruction: Instruction,
+    long_address: u16,
+    syntax: Syntax,
+    address: &dyn Fn(u16) -> String,
+) -> String {
+    match syntax {
+        Syntax::Octo => format_octo(instruction, long_address, address),
+        Syntax::Classic => format_classic(instruction, long_address, address),
+    }
+}
+
+fn format_octo(
+    instruction: Instruction,
+    long_address: u16,
+    address: &dyn Fn(u16) -> String,
+) -> String {
+    use Instruction::*;
+
+    match instruction {
+        Clear => "clear".to_string(),
+        Return => "return".to_string(),
+        ScrollDown { n } => format!("scroll-down {n}"),
+        ScrollUp { n } => format!("scroll-up {n}"),
+        ScrollRight => "scroll-right".to_string(),
+        ScrollLeft => "scroll-left".to_string(),
+        Exit => "exit".to_string(),
+        Lores => "lores".to_string(),
+        Hires => "hires".to_string(),
+        // Octo has no mnemonic for machine code calls
+        Sys { nnn } => format!("0x{:02X} 0x{:02X}", nnn >> 8, nnn & 0xFF),
+        Jump { nnn } => format!("jump {}", address(nnn)),
+        Call { nnn } => format!(":call {}", address(nnn)),
+        // Octo's if runs the next instruction when the condition holds,
+        // so a skip reads as the opposite condition
+        SkipIfEqual { x, nn } => format!("if v{x:x} != 0x{nn:02X} then"),
+        SkipIfNotEqual { x, nn } => format!("if v{x:x} == 0x{nn:02X} then"),
+        SkipIfRegistersEqual { x, y } => format!("if v{x:x} != v{y:x} then"),
+        SaveRange { x, y } => format!("save v{x:x} - v{y:x}"),
+        LoadRange { x, y } => format!("load v{x:x} - v{y:x}"),
+        Set { x, nn } => format!("v{x:x} := 0x{nn:02X}"),
+        Add { x, nn } => format!("v{x:x} += 0x{nn:02X}"),
+        Copy { x, y } => format!("v{x:x} := v{y:x}"),
+        Or { x, y } => format!("v{x:x} |= v{y:x}"),
+        And { x, y } => format!("v{x:x} &= v{y:x}"),
+        Xor { x, y } => format!("v{x:x} ^= v{y:x}"),
+        AddRegisters { x, y } => format!("v{x:x} += v{y:x}"),
+        Subtract { x, y } => format!("v{x:x} -= v{y:x}"),
+        ShiftRight { x, y } => format!("v{x:x} >>= v{y:x}"),
+        SubtractReversed { x, y } => format!("v{x:x} =- v{y:x}"),
+        ShiftLeft { x, y } => format!("v{x:x} <<= v{y:x}"),
+        SkipIfRegistersNotEqual { x, y } => format!("if v{x:x} == v{y:x} then"),
+        SetIndex { nnn } => format!("i := {}", address(nnn)),
+        JumpWithOffset { nnn, .. } => format!("jump0 {}", address(nnn)),
+        Random { x, nn } => format!("v{x:x} := random 0x{nn:02X}"),
+        Draw { x, y, n } => format!("sprite v{x:x} v{y:x} {n}"),
+        SkipIfKey { x } => format!("if v{x:x} -key then"),
+        SkipIfNotKey { x } => format!("if v{x:x} key then"),
+        LoadLongIndex => format!("i := long {}", address(long_address)),
+        SelectPlanes { n } => format!("plane {n}"),
+        LoadAudioPattern => "audio".to_string(),
+        GetDelayTimer { x } => format!("v{x:x} := delay"),
+        WaitForKey { x } => format!("v{x:x} := key"),
+        SetDelayTimer { x } => format!("delay := v{x:x}"),
+        SetSoundTimer { x } => format!("buzzer := v{x:x}"),
+        AddToIndex { x } => format!("i += v{x:x}"),
+        Font { x } => format!("i := hex v{x:x}"),
+        BigFont { x } => format!("i := bighex v{x:x}"),
+        Bcd { x } => format!("bcd v{x:x}"),
+        SetPitch { x } => format!("pitch := v{x:x}"),
+        Store { x } => format!("save v{x:x}"),
+        Load { x } => format!("load v{x:x}"),
+        SaveFlags { x } => format!("saveflags v{x:x}"),
+        LoadFlags { x } => format!("loadflags v{x:x}"),
+        Unknown { opcode } => format!("0x{:02X} 0x{:02X}", opcode >> 8, opcode & 0xFF),
+    }
+}
+
+fn format_classic(
+    instruction: Instruction,
+    long_address: u16,
+    address: &dyn Fn(u16) -> String,
+) -> String {
+    use Instruction::*;
+
+    match instruction {
+        Clear => "CLS".to_string(),
+        Return => "RET".to_string(),
+        ScrollDown { n } => format!("SCD {n}"),
+        ScrollUp { n } => format!("SCU {n}"),
+        ScrollRight => "SCR".to_string(),
+        ScrollLeft => "SCL".to_string(),
+        Exit => "EXIT".to_string(),
+        Lores => "LOW".to_string(),
+        Hires => "HIGH".to_string(),
+        Sys { nnn } => format!("SYS 0x{nnn:03X}"),
+        Jump { nnn } => format!("JP {}", address(nnn)),
+        Call { nnn } => format!("CALL {}", address(nnn)),
+        SkipIfEqual { x, nn } => format!("SE V{x:X}, 0x{nn:02X}"),
+        SkipIfNotEqual { x, nn } => format!("SNE V{x:X}, 0x{nn:02X}"),
+        SkipIfRegistersEqual { x, y } => format!("SE V{x:X}, V{y:X}"),
+        SaveRange { x, y } => format!("SAVE V{x:X}, V{y:X}"),
+        LoadRange { x, y } => format!("LOAD V{x:X}, V{y:X}"),
+        Set { x, nn } => format!("LD V{x:X}, 0x{nn:02X}"),
+        Add { x, nn } => format!("ADD V{x:X}, 0x{nn:02X}"),
+        Copy { x, y } => format!("LD V{x:X}, V{y:X}"),
+        Or { x, y } => format!("OR V{x:X}, V{y:X}"),
+        And { x, y } => format!("AND V{x:X}, V{y:X}"),
+        Xor { x, y } => format!("XOR V{x:X}, V{y:X}"),
+        AddRegisters { x, y } => format!("ADD V{x:X}, V{y:X}"),
+        Subtract { x, y } => format!("SUB V{x:X}, V{y:X}"),
+        ShiftRight { x, y } => format!("SHR V{x:X}, V{y:X}"),
+        SubtractReversed { x, y } => format!("SUBN V{x:X}, V{y:X}"),
+        ShiftLeft { x, y } => format!("SHL V{x:X}, V{y:X}"),
+        SkipIfRegistersNotEqual { x, y } => format!("SNE V{x:X}, V{y:X}"),
+        SetIndex { nnn } => format!("LD I, {}", address(nnn)),
+        JumpWithOffset { nnn, .. } => format!("JP V0, {}", address(nnn)),
+        Random { x, nn } => format!("RND V{x:X}, 0x{nn:02X}"),
+        Draw { x, y, n } => format!("DRW V{x:X}, V{y:X}, {n}"),
+        SkipIfKey { x } => format!("SKP V{x:X}"),
+        SkipIfNotKey { x } => format!("SKNP V{x:X}"),
+        LoadLongIndex => format!("LD I, LONG {}", address(long_address)),
+        SelectPlanes { n } => format!("PLANE {n}"),
+        LoadAudioPattern => "AUDIO".to_string(),
+        GetDelayTimer { x } => format!("LD V{x:X}, DT"),
+        WaitForKey { x } => format!("LD V{x:X}, K"),
+        SetDelayTimer { x } => format!("LD DT, V{x:X}"),
+        SetSoundTimer { x } => format!("LD ST, V{x:X}"),
+        AddToIndex { x } => format!("ADD I, V{x:X}"),
+        Font { x } => format!("LD F, V{x:X}"),
+        BigFont { x } => format!("LD HF, V{x:X}"),
+        Bcd { x } => format!("LD B, V{x:X}"),
+        SetPitch { x } => format!("LD PITCH, V{x:X}"),
+        Store { x } => format!("LD [I], V{x:X}"),
+        Load { x } => format!("LD V{x:X}, [I]"),
+        SaveFlags { x } => format!("LD R, V{x:X}"),
+        LoadFlags { x } => format!("LD V{x:X}, R"),
+        Unknown { opcode } => format!("DW 0x{opcode:04X}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+    use crate::asm::assemble;
+
+    // LD V0, 5; LD I, sprite; CALL sub; JP main; sprite: 0xF0 0x90; sub: RET
+    const ROM: [u8; 12] = [
+        0x60, 0x05, 0xA2, 0x08, 0x22, 0x0A, 0x12, 0x00, 0xF0, 0x90, 0x00, 0xEE,
+    ];
+
+    #[test]
+    fn labels_code_and_data_targets() {
+        let expected = "\
+main:
+\tLD V0, 0x05                 ; 200: 6005
+\tLD I, L208                  ; 202: A208
+\tCALL L20A                   ; 204: 220A
+\tJP main                     ; 206: 1200
+L208:
+\tdb 0xF0, 0x90               ; 208
+L20A:
+\tRET                         ; 20A: 00EE
+";
+        assert_eq!(disassemble(&ROM, Syntax::Classic), expected);
+    }
+
+    #[test]
+    fn prints_octo_syntax() {
+        let out = disassemble(&ROM, Syntax::Octo);
+        assert!(out.starts_with(": main\n\tv0 := 0x05"), "{out}");
+        assert!(out.contains("\t0xF0 0x90"), "{out}");
+    }
+
+    #[test]
+    fn disassembles_a_single_instruction() {
+        assert_eq!(disassemble_at(&ROM, 0x4, Syntax::Classic), "CALL 0x20A");
+    }
+
+    #[test]
+    fn classic_output_reassembles_to_the_same_rom() {
+        let roms = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("roms");
+        let mut paths: Vec<PathBuf> = ["games", "tests"]
+            .iter()
+            .flat_map(|dir| fs::read_dir(roms.join(dir)).unwrap())
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+
+        for path in paths {
+            let rom = fs::read(&path).unwrap();
+            let source = disassemble(&rom, Syntax::Classic);
+            let reassembled = assemble(&source)
+                .unwrap_or_else(|e| panic!("{} doesn't reassemble: {e}", path.display()));
+            assert!(
+                reassembled == rom,
+                "{} reassembles differently",
+                path.display()
+            );
+        }
+    }
+}
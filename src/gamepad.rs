@@ -0,0 +1,121 @@
+use std::io;
+
+use evdev::{AbsoluteAxisCode, Device, EventSummary, KeyCode};
+
+use crate::keymap::Keymap;
+
+/// Button names accepted in a keymap, after the kernel's ```BTN_``` names.
+/// Pads that report the D-pad as a hat rather than buttons still press the Dpad ones.
+const BUTTON_NAMES: [(&str, KeyCode); 17] = [
+    ("DpadUp", KeyCode::BTN_DPAD_UP),
+    ("DpadDown", KeyCode::BTN_DPAD_DOWN),
+    ("DpadLeft", KeyCode::BTN_DPAD_LEFT),
+    ("DpadRight", KeyCode::BTN_DPAD_RIGHT),
+    ("South", KeyCode::BTN_SOUTH),
+    ("East", KeyCode::BTN_EAST),
+    ("North", KeyCode::BTN_NORTH),
+    ("West", KeyCode::BTN_WEST),
+    ("TL", KeyCode::BTN_TL),
+    ("TR", KeyCode::BTN_TR),
+    ("TL2", KeyCode::BTN_TL2),
+    ("TR2", KeyCode::BTN_TR2),
+    ("Select", KeyCode::BTN_SELECT),
+    ("Start", KeyCode::BTN_START),
+    ("Mode", KeyCode::BTN_MODE),
+    ("ThumbL", KeyCode::BTN_THUMBL),
+    ("ThumbR", KeyCode::BTN_THUMBR),
+];
+
+/// A controller read through ```/dev/input/event*```.
+pub struct Gamepad {
+    device: Device,
+    buttons: [Vec<KeyCode>; 16],
+    // buttons held down, including the D-pad hat
+    held: Vec<KeyCode>,
+}
+
+impl Gamepad {
+    /// Opens the first device with gamepad buttons, if there is one we're
+    /// allowed to read.
+    pub fn find(buttonmap: &Keymap) -> Result<Option<Self>, String> {
+        let device = evdev::enumerate().map(|(_, device)| device).find(|device| {
+            device
+                .supported_keys()
+                .is_some_and(|keys| keys.contains(KeyCode::BTN_SOUTH))
+        });
+        device
+            .map(|device| Self::new(device, buttonmap))
+            .transpose()
+    }
+
+    pub fn new(device: Device, buttonmap: &Keymap) -> Result<Self, String> {
+        let mut buttons: [Vec<KeyCode>; 16] = Default::default();
+        for (chip8_key, buttons) in buttons.iter_mut().enumerate() {
+            for name in buttonmap.bindings(chip8_key) {
+                buttons
+                    .push(button_from_name(name).ok_or_else(|| format!("Unknown button {name}"))?);
+            }
+        }
+        // polled once a frame, so reads must not wait for input
+        device
+            .set_nonblocking(true)
+            .map_err(|e| format!("Couldn't set up gamepad: {e}"))?;
+        Ok(Self {
+            device,
+            buttons,
+            held: Vec::new(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        self.device.name().unwrap_or("gamepad")
+    }
+
+    /// Reads every event since the last poll. Fails when the pad is unplugged.
+    pub fn poll(&mut self) -> io::Result<()> {
+        let events = match self.device.fetch_events() {
+            Ok(events) => events,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        for event in events {
+            match event.destructure() {
+                EventSummary::Key(_, code, value) => set_held(&mut self.held, code, value != 0),
+                EventSummary::AbsoluteAxis(_, AbsoluteAxisCode::ABS_HAT0X, value) => {
+                    set_held(&mut self.held, KeyCode::BTN_DPAD_LEFT, value < 0);
+                    set_held(&mut self.held, KeyCode::BTN_DPAD_RIGHT, value > 0);
+                }
+                EventSummary::AbsoluteAxis(_, AbsoluteAxisCode::ABS_HAT0Y, value) => {
+                    set_held(&mut self.held, KeyCode::BTN_DPAD_UP, value < 0);
+                    set_held(&mut self.held, KeyCode::BTN_DPAD_DOWN, value > 0);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Bitmask of the Chip-8 keys held down. A Chip-8 key is down
+    /// when any button bound to it is.
+    pub fn held_keys(&self) -> u16 {
+        self.buttons
+            .iter()
+            .enumerate()
+            .filter(|(_, buttons)| buttons.iter().any(|b| self.held.contains(b)))
+            .fold(0, |held, (chip8_key, _)| held | 1 << chip8_key)
+    }
+}
+
+fn set_held(held: &mut Vec<KeyCode>, button: KeyCode, down: bool) {
+    held.retain(|b| *b != button);
+    if down {
+        held.push(button);
+    }
+}
+
+fn button_from_name(name: &str) -> Option<KeyCode> {
+    BUTTON_NAMES
+        .iter()
+        .find(|(button_name, _)| button_name.eq_ignore_ascii_case(name))
+        .map(|(_, button)| *button)
+}
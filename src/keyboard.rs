@@ -0,0 +1,140 @@
+use chip8::keymap::Keymap;
+use minifb::{Key, Window};
+
+/// Key names accepted in a keymap. Escape, Tab, Backspace and F1-F8
+/// are left out since the window already uses them.
+const KEY_NAMES: [(&str, Key); 74] = [
+    ("0", Key::Key0),
+    ("1", Key::Key1),
+    ("2", Key::Key2),
+    ("3", Key::Key3),
+    ("4", Key::Key4),
+    ("5", Key::Key5),
+    ("6", Key::Key6),
+    ("7", Key::Key7),
+    ("8", Key::Key8),
+    ("9", Key::Key9),
+    ("A", Key::A),
+    ("B", Key::B),
+    ("C", Key::C),
+    ("D", Key::D),
+    ("E", Key::E),
+    ("F", Key::F),
+    ("G", Key::G),
+    ("H", Key::H),
+    ("I", Key::I),
+    ("J", Key::J),
+    ("K", Key::K),
+    ("L", Key::L),
+    ("M", Key::M),
+    ("N", Key::N),
+    ("O", Key::O),
+    ("P", Key::P),
+    ("Q", Key::Q),
+    ("R", Key::R),
+    ("S", Key::S),
+    ("T", Key::T),
+    ("U", Key::U),
+    ("V", Key::V),
+    ("W", Key::W),
+    ("X", Key::X),
+    ("Y", Key::Y),
+    ("Z", Key::Z),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Space", Key::Space),
+    ("Enter", Key::Enter),
+    ("LeftShift", Key::LeftShift),
+    ("RightShift", Key::RightShift),
+    ("LeftCtrl", Key::LeftCtrl),
+    ("RightCtrl", Key::RightCtrl),
+    ("LeftAlt", Key::LeftAlt),
+    ("RightAlt", Key::RightAlt),
+    ("Comma", Key::Comma),
+    ("Period", Key::Period),
+    ("Slash", Key::Slash),
+    ("Semicolon", Key::Semicolon),
+    ("Apostrophe", Key::Apostrophe),
+    ("Minus", Key::Minus),
+    ("Equal", Key::Equal),
+    ("LeftBracket", Key::LeftBracket),
+    ("RightBracket", Key::RightBracket),
+    ("Backslash", Key::Backslash),
+    ("NumPad0", Key::NumPad0),
+    ("NumPad1", Key::NumPad1),
+    ("NumPad2", Key::NumPad2),
+    ("NumPad3", Key::NumPad3),
+    ("NumPad4", Key::NumPad4),
+    ("NumPad5", Key::NumPad5),
+    ("NumPad6", Key::NumPad6),
+    ("NumPad7", Key::NumPad7),
+    ("NumPad8", Key::NumPad8),
+    ("NumPad9", Key::NumPad9),
+    ("NumPadPlus", Key::NumPadPlus),
+    ("NumPadMinus", Key::NumPadMinus),
+    ("NumPadAsterisk", Key::NumPadAsterisk),
+    ("NumPadSlash", Key::NumPadSlash),
+    ("NumPadDot", Key::NumPadDot),
+    ("NumPadEnter", Key::NumPadEnter),
+];
+
+/// A keymap resolved to minifb key codes.
+pub struct Keyboard {
+    keys: [Vec<Key>; 16],
+}
+
+impl Keyboard {
+    pub fn new(keymap: &Keymap) -> Result<Self, String> {
+        let mut keys: [Vec<Key>; 16] = Default::default();
+        for (chip8_key, keys) in keys.iter_mut().enumerate() {
+            for name in keymap.bindings(chip8_key) {
+                keys.push(key_from_name(name).ok_or_else(|| format!("Unknown key {name}"))?);
+            }
+        }
+        Ok(Self { keys })
+    }
+
+    /// Bitmask of the Chip-8 keys held down. A Chip-8 key is down
+    /// when any key bound to it is.
+    pub fn held_keys(&self, window: &Window) -> u16 {
+        self.keys
+            .iter()
+            .enumerate()
+            .filter(|(_, keys)| keys.iter().any(|key| window.is_key_down(*key)))
+            .fold(0, |held, (chip8_key, _)| held | 1 << chip8_key)
+    }
+}
+
+fn key_from_name(name: &str) -> Option<Key> {
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|(_, key)| *key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8::keymap::{Binding, KeyBindings};
+
+    fn keymap(key: &str, name: &str) -> Keymap {
+        let mut keymap = Keymap::default();
+        let bindings = KeyBindings::from([(key.to_string(), Binding::One(name.to_string()))]);
+        keymap.apply(&bindings).unwrap();
+        keymap
+    }
+
+    #[test]
+    fn resolves_names_in_any_case() {
+        let keyboard = Keyboard::new(&keymap("5", "numpad5")).unwrap();
+        assert_eq!(keyboard.keys[5], [Key::NumPad5]);
+    }
+
+    #[test]
+    fn rejects_unknown_key_names() {
+        let e = Keyboard::new(&keymap("5", "Hyper")).err().unwrap();
+        assert_eq!(e, "Unknown key Hyper");
+    }
+}
@@ -0,0 +1,206 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::Deserialize;
+
+/// Chip-8 keys in the order they sit on the COSMAC VIP keypad.
+const KEYPAD_LAYOUT: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// The left of a QWERTY keyboard laid out like the keypad:
+///
+/// ```text
+/// 1 2 3 4      1 2 3 C
+/// Q W E R  ->  4 5 6 D
+/// A S D F      7 8 9 E
+/// Z X C V      A 0 B F
+/// ```
+const DEFAULT_KEYS: [&str; 16] = [
+    "X", "1", "2", "3", "Q", "W", "E", "A", "S", "D", "Z", "C", "4", "R", "F", "V",
+];
+
+/// The D-pad as the 2/4/6/8 cross, with the bottom face button on 5 in the middle.
+const DEFAULT_BUTTONS: [(usize, &str); 5] = [
+    (0x2, "DpadUp"),
+    (0x4, "DpadLeft"),
+    (0x5, "South"),
+    (0x6, "DpadRight"),
+    (0x8, "DpadDown"),
+];
+
+/// Keys for the actions a rom database entry names, e.g. ```"left": 4```.
+pub const KEYBOARD_HINTS: [(&str, &str); 6] = [
+    ("up", "Up"),
+    ("down", "Down"),
+    ("left", "Left"),
+    ("right", "Right"),
+    ("a", "Space"),
+    ("b", "LeftShift"),
+];
+
+/// Same as ```KEYBOARD_HINTS```, for gamepad buttons.
+pub const GAMEPAD_HINTS: [(&str, &str); 6] = [
+    ("up", "DpadUp"),
+    ("down", "DpadDown"),
+    ("left", "DpadLeft"),
+    ("right", "DpadRight"),
+    ("a", "South"),
+    ("b", "East"),
+];
+
+/// One physical key, or a list of them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Binding {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Binding {
+    /// The key names, however many were given.
+    pub fn names(&self) -> &[String] {
+        match self {
+            Binding::One(name) => std::slice::from_ref(name),
+            Binding::Many(names) => names,
+        }
+    }
+}
+
+/// Bindings from a config file, keyed by the Chip-8 key in hex.
+pub type KeyBindings = BTreeMap<String, Binding>;
+
+/// The physical keys or buttons bound to each Chip-8 key, 0 to F. They're
+/// stored by name so each frontend can look up its own key codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    keys: [Vec<String>; 16],
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            keys: DEFAULT_KEYS.map(|name| vec![name.to_string()]),
+        }
+    }
+}
+
+impl Keymap {
+    /// The default gamepad layout.
+    pub fn gamepad() -> Self {
+        let mut keys: [Vec<String>; 16] = Default::default();
+        for (chip8_key, name) in DEFAULT_BUTTONS {
+            keys[chip8_key].push(name.to_string());
+        }
+        Self { keys }
+    }
+
+    /// Replaces the bindings for the Chip-8 keys listed, leaving the rest alone.
+    pub fn apply(&mut self, bindings: &KeyBindings) -> Result<(), String> {
+        for (key, binding) in bindings {
+            let chip8_key = u8::from_str_radix(key, 16)
+                .ok()
+                .filter(|k| *k < 16)
+                .ok_or_else(|| format!("Invalid Chip-8 key {key}, expected 0 to F"))?;
+            self.keys[chip8_key as usize] = binding.names().to_vec();
+        }
+        Ok(())
+    }
+
+    /// Moves the keys named in ```names``` to the Chip-8 keys a rom's
+    /// database entry gives for each action. Actions without a name are skipped.
+    pub fn apply_hints(&mut self, hints: &BTreeMap<String, u8>, names: &[(&str, &str)]) {
+        for (action, chip8_key) in hints {
+            let Some((_, name)) = names.iter().find(|(a, _)| a == action) else {
+                continue;
+            };
+            for keys in &mut self.keys {
+                keys.retain(|key| !key.eq_ignore_ascii_case(name));
+            }
+            if let Some(keys) = self.keys.get_mut(*chip8_key as usize) {
+                keys.push(name.to_string());
+            }
+        }
+    }
+
+    /// Names of the physical keys or buttons bound to ```chip8_key```.
+    pub fn bindings(&self, chip8_key: usize) -> &[String] {
+        &self.keys[chip8_key]
+    }
+}
+
+/// Draws the keypad with each key's bindings.
+impl fmt::Display for Keymap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cells = KEYPAD_LAYOUT.map(|row| {
+            row.map(|key| {
+                let names = self.keys[key].join(",");
+                format!("{key:X}: {}", if names.is_empty() { "-" } else { &names })
+            })
+        });
+        let width = cells
+            .iter()
+            .flatten()
+            .map(|cell| cell.len())
+            .max()
+            .unwrap_or(0);
+        for row in cells {
+            let line: Vec<String> = row.iter().map(|cell| format!("{cell:width$}")).collect();
+            writeln!(f, "{}", line.join("  ").trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings(pairs: &[(&str, &[&str])]) -> KeyBindings {
+        pairs
+            .iter()
+            .map(|(key, names)| {
+                let names = names.iter().map(|name| name.to_string()).collect();
+                (key.to_string(), Binding::Many(names))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn applies_only_the_keys_listed() {
+        let mut keymap = Keymap::default();
+        keymap
+            .apply(&bindings(&[("5", &["W", "Up"]), ("a", &[])]))
+            .unwrap();
+        assert_eq!(keymap.bindings(0x5), ["W", "Up"]);
+        assert!(keymap.bindings(0xA).is_empty());
+        assert_eq!(keymap.bindings(0x4), ["Q"]);
+    }
+
+    #[test]
+    fn rejects_keys_that_arent_a_hex_digit() {
+        for key in ["10", "G", "-1", ""] {
+            let e = Keymap::default()
+                .apply(&bindings(&[(key, &["W"])]))
+                .unwrap_err();
+            assert_eq!(e, format!("Invalid Chip-8 key {key}, expected 0 to F"));
+        }
+    }
+
+    #[test]
+    fn hints_move_keys_from_wherever_they_were() {
+        let mut keymap = Keymap::default();
+        keymap.apply(&bindings(&[("2", &["Q", "Left"])])).unwrap();
+        let hints = BTreeMap::from([("left".to_string(), 4), ("jump".to_string(), 5)]);
+        keymap.apply_hints(&hints, &KEYBOARD_HINTS);
+        assert_eq!(keymap.bindings(0x2), ["Q"]);
+        assert_eq!(keymap.bindings(0x4), ["Q", "Left"]);
+        assert_eq!(
+            keymap.bindings(0x5),
+            ["W"],
+            "actions without a name are skipped"
+        );
+    }
+}
@@ -0,0 +1,9 @@
+//! Tools built around the Chip-8 interpreter in ```chip8-core```.
+
+pub mod asm;
+pub mod beep;
+pub mod config;
+pub mod disasm;
+#[cfg(target_os = "linux")]
+pub mod gamepad;
+pub mod keymap;
@@ -1,45 +1,188 @@
-use crate::{emulator::Chip8, roms::get_desired_rom};
+use crate::{args::Args, debugger::Debugger, keyboard::Keyboard, tty::Bell};
+#[cfg(target_os = "linux")]
+use chip8::gamepad::Gamepad;
+use chip8::{beep::Beep, config::Config};
+use chip8_core::{
+    emulator::{Chip8, HIRES_HEIGHT, HIRES_WIDTH},
+    headless::{Headless, ImageFormat, encode_display},
+    rewind::Rewind,
+    romdb::{RomDatabase, RomEntry},
+    scheduler::{Scheduler, Timing},
+};
 use minifb::{Key, KeyRepeat, Window, WindowOptions};
-use std::{thread, time::Duration};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+    time::Instant,
+};
 
-mod emulator;
-mod roms;
+mod args;
+mod debugger;
+mod keyboard;
+mod tty;
 
-const DISPLAY_WIDTH: usize = 64;
-const DISPLAY_HEIGHT: usize = 32;
-const DISPLAY_SIZE: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT;
+const FRAMES_PER_SECOND: usize = 60;
+/// Speed multiplier while Tab is held.
+const FAST_FORWARD_SPEED: f64 = 4.0;
 
 fn main() {
-    let mut em = Chip8::new();
-    let rom_path = get_desired_rom();
-    em.load_rom(rom_path);
+    let mut args = Args::parse();
+    // headless runs are for machines without a sound card
+    let mut em = if args.headless.is_some() || args.print_keymap {
+        Chip8::new()
+    } else if args.tty.is_some() {
+        Chip8::with_audio(Box::new(Bell))
+    } else {
+        Chip8::with_audio(Box::new(Beep::with_tone(args.tone)))
+    };
+    em.error_policy = args.error_policy;
+    if let Err(e) = em.load_rom(&args.rom_path) {
+        eprintln!("{e}");
+        exit(1);
+    }
+
+    let database = (!args.no_database).then(RomDatabase::bundled);
+    let rom = database.as_ref().and_then(|db| db.lookup(&em.rom_hash()));
+    if let Some(rom) = &rom {
+        println!("{}", describe(rom));
+    }
+    // flags on the command line win over the database
+    em.quirks = match args.platform {
+        Some(platform) => platform.quirks(),
+        None => rom.and_then(|rom| rom.quirks()).unwrap_or_default(),
+    };
+    if let Some(depth) = args.stack_depth {
+        em.quirks.stack_depth = depth;
+    }
+    em.quirks.memory_mapped_stack |= args.memory_mapped_stack;
+    if let Some(rom) = &rom {
+        em.palette = rom.palette(em.palette);
+    }
+    let timing = args
+        .timing
+        .or_else(|| Timing::per_frame(rom?.tickrate()?))
+        .unwrap_or_default();
+    if let Some(headless) = &mut args.headless {
+        headless.timing = timing;
+    }
+
+    let config = load_config(args.config.as_deref());
+    let hints = rom.map(|rom| rom.rom.keys.clone()).unwrap_or_default();
+    let (keymap, buttonmap) = config
+        .keymap(&em.rom_hash(), &hints)
+        .and_then(|keymap| Ok((keymap, config.buttonmap(&em.rom_hash(), &hints)?)))
+        .unwrap_or_else(|e| {
+            eprintln!("{e}");
+            exit(1);
+        });
+    if args.print_keymap {
+        print!("Keyboard:\n{keymap}\nGamepad:\n{buttonmap}");
+        return;
+    }
+
+    if args.debug {
+        Debugger::new(em, timing).repl();
+        return;
+    }
+    if let Some(headless) = &args.headless {
+        run_headless(&mut em, headless, args.screenshot.as_deref());
+        return;
+    }
 
+    let keyboard = Keyboard::new(&keymap).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(1);
+    });
+    let title = match &rom {
+        Some(rom) => format!("Chip-8 - {}", rom.program.title),
+        None => "Chip-8".to_string(),
+    };
+    if let Some(style) = args.tty {
+        if let Err(e) = tty::run(&mut em, &args, style, &keymap, timing, &title) {
+            eprintln!("{e}");
+            exit(1);
+        }
+        return;
+    }
+
+    #[cfg(target_os = "linux")]
+    let mut gamepad = match Gamepad::find(&buttonmap) {
+        Ok(gamepad) => gamepad.inspect(|pad| println!("Using gamepad {}", pad.name())),
+        Err(e) => {
+            eprintln!("{e}");
+            exit(1);
+        }
+    };
     let mut window = Window::new(
-        "Chip-8",
-        DISPLAY_WIDTH,
-        DISPLAY_HEIGHT,
+        &title,
+        HIRES_WIDTH,
+        HIRES_HEIGHT,
         WindowOptions {
-            scale: minifb::Scale::X8,
+            // the buffer is stretched to fit, so lores looks the same size
+            scale: minifb::Scale::X4,
+            resize: true,
             scale_mode: minifb::ScaleMode::AspectRatioStretch,
             ..WindowOptions::default()
         },
     )
     .unwrap();
+    // update() sleeps out the rest of the frame, the scheduler keeps emulated time
+    window.set_target_fps(FRAMES_PER_SECOND);
+
+    let mut rewind = Rewind::new(args.rewind_seconds * FRAMES_PER_SECOND);
+    let mut scheduler = Scheduler::new(timing);
+    let mut last_frame = Instant::now();
 
-    while window.is_open() && !window.is_key_pressed(Key::Escape, KeyRepeat::No) {
-        em.decrement_timers();
+    while window.is_open() && !window.is_key_pressed(Key::Escape, KeyRepeat::No) && !em.has_exited()
+    {
+        handle_save_state_keys(&window, &mut em, &args.rom_path);
 
-        for _ in 0..12 {
-            em.keys
-                .iter_mut()
-                .for_each(|(key, data)| data.pressed = window.is_key_down(*key));
-            em.run();
+        // holding backspace plays the recorded frames backwards instead of running
+        let rewound = window.is_key_down(Key::Backspace)
+            && match rewind.pop() {
+                Some(snapshot) => em.restore(&snapshot).is_ok(),
+                None => false,
+            };
+
+        let now = Instant::now();
+        let elapsed = now - last_frame;
+        last_frame = now;
+
+        if !rewound {
+            scheduler.speed = if window.is_key_down(Key::Tab) {
+                args.speed * FAST_FORWARD_SPEED
+            } else {
+                args.speed
+            };
+
+            #[allow(unused_mut)]
+            let mut keys = keyboard.held_keys(&window);
+            #[cfg(target_os = "linux")]
+            if let Some(pad) = &mut gamepad {
+                match pad.poll() {
+                    Ok(()) => keys |= pad.held_keys(),
+                    Err(e) => {
+                        eprintln!("Gamepad disconnected: {e}");
+                        gamepad = None;
+                    }
+                }
+            }
+
+            match scheduler.advance(&mut em, elapsed, keys) {
+                Ok(0) => {}
+                Ok(_) => rewind.push(&em.snapshot()),
+                // a halted emulator keeps its last frame on screen
+                Err(e) => eprintln!("Halted: {e}"),
+            }
+            for e in em.take_logged_errors() {
+                eprintln!("{e}");
+            }
         }
 
-        thread::sleep(Duration::from_millis(16));
         if em.update_display {
             window
-                .update_with_buffer(em.get_display(), DISPLAY_WIDTH, DISPLAY_HEIGHT)
+                .update_with_buffer(em.framebuffer(), em.width(), em.height())
                 .unwrap();
             em.update_display = false;
         } else {
@@ -47,3 +190,89 @@ fn main() {
         }
     }
 }
+
+/// Title, authors, release year and platform, e.g. "Brix by Andreas Gustafsson (1990), Original CHIP-8".
+fn describe(rom: &RomEntry) -> String {
+    let mut description = rom.program.title.clone();
+    if !rom.program.authors.is_empty() {
+        description += &format!(" by {}", rom.program.authors.join(", "));
+    }
+    if let Some(release) = &rom.program.release {
+        description += &format!(" ({release})");
+    }
+    if let Some(platform) = rom.platform {
+        description += &format!(", {}", platform.name);
+    }
+    description
+}
+
+/// The config passed with ```--config```, or the default one if it exists.
+fn load_config(path: Option<&Path>) -> Config {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => match Config::default_path() {
+            Some(path) if path.exists() => path,
+            _ => return Config::default(),
+        },
+    };
+    Config::load(&path).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(1);
+    })
+}
+
+/// F1-F4 save to slots 1-4, F5-F8 load them back.
+fn handle_save_state_keys(window: &Window, em: &mut Chip8, rom_path: &Path) {
+    const SAVE_KEYS: [Key; 4] = [Key::F1, Key::F2, Key::F3, Key::F4];
+    const LOAD_KEYS: [Key; 4] = [Key::F5, Key::F6, Key::F7, Key::F8];
+
+    for slot in 0..SAVE_KEYS.len() {
+        if window.is_key_pressed(SAVE_KEYS[slot], KeyRepeat::No) {
+            let path = save_state_path(rom_path, slot + 1);
+            match em.save_state(&path) {
+                Ok(()) => println!("Saved state to {}", path.display()),
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+        if window.is_key_pressed(LOAD_KEYS[slot], KeyRepeat::No) {
+            let path = save_state_path(rom_path, slot + 1);
+            match em.load_state(&path) {
+                Ok(()) => println!("Loaded state from {}", path.display()),
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+    }
+}
+
+/// Save states live next to the rom, e.g. PONG.state1
+fn save_state_path(rom_path: &Path, slot: usize) -> PathBuf {
+    let mut path = rom_path.as_os_str().to_owned();
+    path.push(format!(".state{slot}"));
+    PathBuf::from(path)
+}
+
+fn run_headless(em: &mut Chip8, headless: &Headless, screenshot: Option<&Path>) {
+    let (frames, reason) = headless.run(em);
+    for e in em.take_logged_errors() {
+        eprintln!("{e}");
+    }
+    eprintln!(
+        "Stopped after {frames} frames: {reason} at pc {:#05X}",
+        em.pc()
+    );
+
+    let format = screenshot.map_or(ImageFormat::Ascii, ImageFormat::from_path);
+    let image = encode_display(em, format).unwrap_or_else(|e| {
+        eprintln!("Could not encode display: {e}");
+        exit(1);
+    });
+    match screenshot {
+        Some(path) => {
+            if let Err(e) = fs::write(path, image) {
+                eprintln!("Could not write {}: {e}", path.display());
+                exit(1);
+            }
+        }
+        None => print!("{}", String::from_utf8_lossy(&image)),
+    }
+}
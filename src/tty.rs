@@ -0,0 +1,630 @@
+//! Runs a rom inside the terminal instead of a window, so it can be played
+//! over SSH.
+
+use std::{
+    env,
+    io::{self, Stdout, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use chip8::keymap::Keymap;
+use chip8_core::{
+    audio::AudioSink,
+    emulator::Chip8,
+    rewind::Rewind,
+    scheduler::{Scheduler, Timing},
+};
+use crossterm::{
+    cursor::{self, MoveTo},
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers,
+        KeyboardEnhancementFlags, ModifierKeyCode, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use crate::{FAST_FORWARD_SPEED, FRAMES_PER_SECOND, args::Args, save_state_path};
+
+/// Without key release events a key counts as held this long after it's
+/// pressed, which covers the terminal's delay before it starts repeating...
+const FIRST_PRESS_HOLD: Duration = Duration::from_millis(500);
+/// ...and this long after each repeat.
+const REPEAT_HOLD: Duration = Duration::from_millis(100);
+
+/// Characters named the way a keymap names them.
+const CHAR_NAMES: [(char, &str); 11] = [
+    (' ', "Space"),
+    (',', "Comma"),
+    ('.', "Period"),
+    ('/', "Slash"),
+    (';', "Semicolon"),
+    ('\'', "Apostrophe"),
+    ('-', "Minus"),
+    ('=', "Equal"),
+    ('[', "LeftBracket"),
+    (']', "RightBracket"),
+    ('\\', "Backslash"),
+];
+
+/// Same as ```CHAR_NAMES```, for the number pad.
+const KEYPAD_NAMES: [(char, &str); 5] = [
+    ('+', "NumPadPlus"),
+    ('-', "NumPadMinus"),
+    ('*', "NumPadAsterisk"),
+    ('/', "NumPadSlash"),
+    ('.', "NumPadDot"),
+];
+
+/// Bit of each dot in a braille character, by row and column.
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// How pixels are packed into character cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// An upper half block colored with the top pixel on a background of
+    /// the bottom one, so each cell shows 1x2 pixels in full color.
+    HalfBlocks,
+    /// Braille dots, 2x4 pixels a cell. Hires fits in 64 columns, but each
+    /// cell only has one color for its lit pixels.
+    Braille,
+}
+
+impl Style {
+    /// Width and height in pixels of one character cell.
+    fn cell_size(self) -> (usize, usize) {
+        match self {
+            Style::HalfBlocks => (1, 2),
+            Style::Braille => (2, 4),
+        }
+    }
+}
+
+/// Rings the terminal bell when a tone starts. A terminal can't hold a tone,
+/// but unlike an audio device the bell makes it over SSH.
+pub struct Bell;
+
+impl AudioSink for Bell {
+    fn start_tone(&mut self) {
+        let mut out = io::stdout();
+        let _ = out.write_all(b"\x07").and_then(|()| out.flush());
+    }
+    fn stop_tone(&mut self) {}
+    fn set_pattern(&mut self, _pattern: [u8; 16]) {}
+    fn set_pitch(&mut self, _pitch: u8) {}
+}
+
+/// Runs ```em``` until Escape or Ctrl-C is pressed or the rom exits.
+pub fn run(
+    em: &mut Chip8,
+    args: &Args,
+    style: Style,
+    keymap: &Keymap,
+    timing: Timing,
+    title: &str,
+) -> io::Result<()> {
+    let mut terminal = Terminal::open()?;
+    let mut keys = HeldKeys::new(terminal.reports_releases);
+    let mut screen = Screen::new(style);
+    let mut rewind = Rewind::new(args.rewind_seconds * FRAMES_PER_SECOND);
+    let mut scheduler = Scheduler::new(timing);
+    let frame = Duration::from_secs(1) / FRAMES_PER_SECOND as u32;
+    let mut last_frame = Instant::now();
+    let mut status = format!("{title} - Esc quits, F1-F4 save, F5-F8 load");
+
+    while !em.has_exited() {
+        // read input until the next frame is due
+        let next_frame = last_frame + frame;
+        while event::poll(next_frame.saturating_duration_since(Instant::now()))? {
+            match event::read()? {
+                Event::Key(key) => {
+                    let quit = key.code == KeyCode::Esc
+                        || key.code == KeyCode::Char('c')
+                            && key.modifiers.contains(KeyModifiers::CONTROL);
+                    if quit && key.kind != KeyEventKind::Release {
+                        return Ok(());
+                    }
+                    if key.kind == KeyEventKind::Press
+                        && let Some(message) = handle_save_state_key(em, key.code, &args.rom_path)
+                    {
+                        status = message;
+                    }
+                    if let Some(name) = key_name(&key) {
+                        match key.kind {
+                            KeyEventKind::Release => keys.release(&name),
+                            KeyEventKind::Press | KeyEventKind::Repeat => {
+                                keys.press(name, Instant::now())
+                            }
+                        }
+                    }
+                }
+                Event::Resize(..) => screen.redraw = true,
+                _ => {}
+            }
+        }
+
+        let now = Instant::now();
+        let elapsed = now - last_frame;
+        last_frame = now;
+        keys.expire(now);
+
+        // holding backspace plays the recorded frames backwards instead of running
+        let rewound = keys.is_down("Backspace")
+            && match rewind.pop() {
+                Some(snapshot) => em.restore(&snapshot).is_ok(),
+                None => false,
+            };
+        if !rewound {
+            scheduler.speed = if keys.is_down("Tab") {
+                args.speed * FAST_FORWARD_SPEED
+            } else {
+                args.speed
+            };
+            match scheduler.advance(em, elapsed, keys.chip8_keys(keymap)) {
+                Ok(0) => {}
+                Ok(_) => rewind.push(&em.snapshot()),
+                // a halted emulator keeps its last frame on screen
+                Err(e) => status = format!("Halted: {e}"),
+            }
+            // the status line only has room for the latest
+            if let Some(e) = em.take_logged_errors().pop() {
+                status = e.to_string();
+            }
+        }
+
+        // only the cells that changed are sent, so this is cheap to do every frame
+        screen.draw(&mut terminal.out, em, &status)?;
+        em.update_display = false;
+    }
+    Ok(())
+}
+
+/// F1-F4 save to slots 1-4, F5-F8 load them back. Returns what happened,
+/// as printing would scribble over the display.
+fn handle_save_state_key(em: &mut Chip8, code: KeyCode, rom_path: &Path) -> Option<String> {
+    let KeyCode::F(key @ 1..=8) = code else {
+        return None;
+    };
+    let slot = (key as usize - 1) % 4 + 1;
+    let path = save_state_path(rom_path, slot);
+    let message = if key <= 4 {
+        match em.save_state(&path) {
+            Ok(()) => format!("Saved state to {}", path.display()),
+            Err(e) => e.to_string(),
+        }
+    } else {
+        match em.load_state(&path) {
+            Ok(()) => format!("Loaded state from {}", path.display()),
+            Err(e) => e.to_string(),
+        }
+    };
+    Some(message)
+}
+
+/// The keymap name of a key, e.g. "A", "Up" or "NumPad5". Modifiers and
+/// the number pad are only told apart by terminals that report releases.
+fn key_name(key: &KeyEvent) -> Option<String> {
+    let keypad = key.state.contains(KeyEventState::KEYPAD);
+    let name = match key.code {
+        KeyCode::Char(c) if keypad && c.is_ascii_digit() => format!("NumPad{c}"),
+        KeyCode::Char(c) if keypad => KEYPAD_NAMES.iter().find(|(k, _)| *k == c)?.1.to_string(),
+        KeyCode::Char(c) if c.is_ascii_alphanumeric() => c.to_ascii_uppercase().to_string(),
+        KeyCode::Char(c) => CHAR_NAMES.iter().find(|(k, _)| *k == c)?.1.to_string(),
+        KeyCode::Enter if keypad => "NumPadEnter".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Modifier(modifier) => match modifier {
+            ModifierKeyCode::LeftShift => "LeftShift",
+            ModifierKeyCode::RightShift => "RightShift",
+            ModifierKeyCode::LeftControl => "LeftCtrl",
+            ModifierKeyCode::RightControl => "RightCtrl",
+            ModifierKeyCode::LeftAlt => "LeftAlt",
+            ModifierKeyCode::RightAlt => "RightAlt",
+            _ => return None,
+        }
+        .to_string(),
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Raw mode on the alternate screen. Dropping it puts the terminal back,
+/// even when unwinding from a panic.
+struct Terminal {
+    out: Stdout,
+    /// The terminal speaks the kitty keyboard protocol and sends key releases.
+    reports_releases: bool,
+}
+
+impl Terminal {
+    fn open() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut terminal = Self {
+            out: io::stdout(),
+            reports_releases: false,
+        };
+        if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            execute!(
+                terminal.out,
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                        | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+                )
+            )?;
+            terminal.reports_releases = true;
+        }
+        execute!(terminal.out, EnterAlternateScreen, cursor::Hide)?;
+        Ok(terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if self.reports_releases {
+            let _ = execute!(self.out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(self.out, ResetColor, cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct HeldKey {
+    name: String,
+    pressed: Instant,
+    last_seen: Instant,
+}
+
+/// Keys down by name. Most terminals only send presses, repeating them
+/// while a key is held, so releases are guessed from when they stop.
+struct HeldKeys {
+    keys: Vec<HeldKey>,
+    reports_releases: bool,
+}
+
+impl HeldKeys {
+    fn new(reports_releases: bool) -> Self {
+        Self {
+            keys: Vec::new(),
+            reports_releases,
+        }
+    }
+
+    fn press(&mut self, name: String, now: Instant) {
+        match self.keys.iter_mut().find(|key| key.name == name) {
+            Some(key) => key.last_seen = now,
+            None => self.keys.push(HeldKey {
+                name,
+                pressed: now,
+                last_seen: now,
+            }),
+        }
+    }
+
+    fn release(&mut self, name: &str) {
+        self.keys.retain(|key| key.name != name);
+    }
+
+    /// Lets go of keys that haven't repeated in time.
+    fn expire(&mut self, now: Instant) {
+        if self.reports_releases {
+            return;
+        }
+        self.keys.retain(|key| {
+            let hold = if key.last_seen > key.pressed {
+                REPEAT_HOLD
+            } else {
+                FIRST_PRESS_HOLD
+            };
+            now - key.last_seen < hold
+        });
+    }
+
+    fn is_down(&self, name: &str) -> bool {
+        self.keys
+            .iter()
+            .any(|key| key.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Bitmask of the Chip-8 keys held down.
+    fn chip8_keys(&self, keymap: &Keymap) -> u16 {
+        (0..16)
+            .filter(|&chip8_key| {
+                keymap
+                    .bindings(chip8_key)
+                    .iter()
+                    .any(|name| self.is_down(name))
+            })
+            .fold(0, |held, chip8_key| held | 1 << chip8_key)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    ch: char,
+    fg: u32,
+    bg: u32,
+}
+
+/// Draws the framebuffer as character cells, sending only the cells that
+/// changed since the last draw to keep the output small over SSH.
+struct Screen {
+    style: Style,
+    /// The terminal takes 24 bit colors. Otherwise the nearest of the 256
+    /// color palette is used.
+    truecolor: bool,
+    /// What's on the terminal now.
+    cells: Vec<Cell>,
+    columns: usize,
+    status: String,
+    /// Clear and draw everything next time, e.g. after a resize.
+    redraw: bool,
+    /// Shown instead of the display while the terminal is too small for it.
+    too_small: Option<String>,
+}
+
+impl Screen {
+    fn new(style: Style) -> Self {
+        Self {
+            style,
+            truecolor: env::var("COLORTERM").is_ok_and(|v| v == "truecolor" || v == "24bit"),
+            cells: Vec::new(),
+            columns: 0,
+            status: String::new(),
+            redraw: true,
+            too_small: None,
+        }
+    }
+
+    fn draw(&mut self, out: &mut Stdout, em: &Chip8, status: &str) -> io::Result<()> {
+        let (cell_width, cell_height) = self.style.cell_size();
+        let columns = em.width() / cell_width;
+        let rows = em.height() / cell_height;
+        let cells = self.cells(em);
+
+        if self.redraw || columns != self.columns {
+            let (width, height) = terminal::size()?;
+            if (width as usize) < columns || (height as usize) <= rows {
+                // skip frames until it's big enough rather than drawing a garbled display,
+                // still redrawing so the size is checked again next frame
+                self.redraw = true;
+                let message = format!("Make the terminal at least {columns}x{}", rows + 1);
+                if self.too_small.as_ref() != Some(&message) {
+                    queue!(
+                        out,
+                        ResetColor,
+                        Clear(ClearType::All),
+                        MoveTo(0, 0),
+                        Print(&message)
+                    )?;
+                    self.too_small = Some(message);
+                }
+                return out.flush();
+            }
+
+            queue!(out, ResetColor, Clear(ClearType::All))?;
+            self.redraw = false;
+            self.too_small = None;
+            self.columns = columns;
+            self.cells.clear();
+            self.status.clear();
+        }
+
+        let mut cursor = None;
+        let mut colors = None;
+        for (index, cell) in cells.iter().enumerate() {
+            if self.cells.get(index) == Some(cell) {
+                continue;
+            }
+            let (x, y) = (index % columns, index / columns);
+            if cursor != Some((x, y)) {
+                queue!(out, MoveTo(x as u16, y as u16))?;
+            }
+            if colors != Some((cell.fg, cell.bg)) {
+                queue!(
+                    out,
+                    SetForegroundColor(self.color(cell.fg)),
+                    SetBackgroundColor(self.color(cell.bg))
+                )?;
+                colors = Some((cell.fg, cell.bg));
+            }
+            queue!(out, Print(cell.ch))?;
+            cursor = Some((x + 1, y));
+        }
+        self.cells = cells;
+
+        if self.status != status {
+            queue!(
+                out,
+                MoveTo(0, rows as u16),
+                ResetColor,
+                Clear(ClearType::CurrentLine),
+                Print(status)
+            )?;
+            self.status = status.to_string();
+        }
+        out.flush()
+    }
+
+    /// The framebuffer packed into cells, row by row.
+    fn cells(&self, em: &Chip8) -> Vec<Cell> {
+        let (width, height) = (em.width(), em.height());
+        let pixels = em.framebuffer();
+        let background = em.palette[0];
+        let pixel = |x: usize, y: usize| pixels[y * width + x];
+
+        match self.style {
+            Style::HalfBlocks => (0..height / 2)
+                .flat_map(|row| (0..width).map(move |x| (x, row * 2)))
+                .map(|(x, y)| Cell {
+                    ch: '▀',
+                    fg: pixel(x, y),
+                    bg: pixel(x, y + 1),
+                })
+                .collect(),
+            Style::Braille => (0..height / 4)
+                .flat_map(|row| (0..width / 2).map(move |column| (column * 2, row * 4)))
+                .map(|(x, y)| {
+                    let mut dots = 0;
+                    let mut fg = None;
+                    for (dy, row) in BRAILLE_DOTS.iter().enumerate() {
+                        for (dx, dot) in row.iter().enumerate() {
+                            let color = pixel(x + dx, y + dy);
+                            if color != background {
+                                dots |= dot;
+                                fg.get_or_insert(color);
+                            }
+                        }
+                    }
+                    Cell {
+                        ch: char::from_u32(0x2800 + dots).unwrap(),
+                        fg: fg.unwrap_or(background),
+                        bg: background,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn color(&self, rgb: u32) -> Color {
+        let [_, r, g, b] = rgb.to_be_bytes();
+        if self.truecolor {
+            Color::Rgb { r, g, b }
+        } else {
+            // nearest entry of the 6x6x6 cube in the 256 color palette
+            let level = |c: u8| (c as u16 * 5 + 127) / 255;
+            Color::AnsiValue((16 + 36 * level(r) + 6 * level(g) + level(b)) as u8)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(style: Style, truecolor: bool) -> Screen {
+        Screen {
+            truecolor,
+            ..Screen::new(style)
+        }
+    }
+
+    /// A machine showing the font's 0 in the top left, white on black.
+    fn zero() -> Chip8 {
+        let mut em = Chip8::new();
+        em.palette = [0x000000, 0xFFFFFF, 0xFFFFFF, 0xFFFFFF];
+        // V0 = 0, I = the 0 sprite, draw it at 0,0
+        em.load_rom_bytes(&[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05])
+            .unwrap();
+        // the draw waits for the vertical blank on the VIP
+        em.decrement_timers();
+        for _ in 0..3 {
+            em.step().unwrap();
+        }
+        em
+    }
+
+    #[test]
+    fn half_blocks_stack_two_pixels_a_cell() {
+        let cells = screen(Style::HalfBlocks, true).cells(&zero());
+        assert_eq!(cells.len(), 64 * 16);
+        let cell = |fg, bg| Cell { ch: '▀', fg, bg };
+        // the 0 is F0 90 90 90 F0
+        assert_eq!(cells[0], cell(0xFFFFFF, 0xFFFFFF));
+        assert_eq!(cells[1], cell(0xFFFFFF, 0));
+        assert_eq!(cells[64 + 1], cell(0, 0));
+        assert_eq!(cells[64 + 3], cell(0xFFFFFF, 0xFFFFFF));
+        assert_eq!(cells[64 * 2 + 1], cell(0xFFFFFF, 0));
+    }
+
+    #[test]
+    fn braille_packs_eight_pixels_a_cell() {
+        let cells = screen(Style::Braille, true).cells(&zero());
+        assert_eq!(cells.len(), 32 * 8);
+        // the left column lit and the top of the right one
+        assert_eq!(
+            cells[0],
+            Cell {
+                ch: '⡏',
+                fg: 0xFFFFFF,
+                bg: 0
+            }
+        );
+        // the right half of the 0, in the other corners
+        assert_eq!(cells[1].ch, '⢹');
+        assert_eq!(cells[32].ch, '⠉');
+        assert_eq!(
+            cells[2],
+            Cell {
+                ch: '⠀',
+                fg: 0,
+                bg: 0
+            }
+        );
+    }
+
+    #[test]
+    fn colors_fall_back_to_the_256_color_cube() {
+        assert_eq!(
+            screen(Style::HalfBlocks, true).color(0xFF6600),
+            Color::Rgb {
+                r: 0xFF,
+                g: 0x66,
+                b: 0x00
+            }
+        );
+        let screen = screen(Style::HalfBlocks, false);
+        assert_eq!(screen.color(0x000000), Color::AnsiValue(16));
+        assert_eq!(screen.color(0xFFFFFF), Color::AnsiValue(231));
+        assert_eq!(screen.color(0xFF6600), Color::AnsiValue(208));
+        assert_eq!(screen.color(0x0000FF), Color::AnsiValue(21));
+    }
+
+    #[test]
+    fn presses_are_held_until_they_stop_repeating() {
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+        let mut keys = HeldKeys::new(false);
+
+        keys.press("W".to_string(), start);
+        keys.expire(ms(499));
+        assert!(keys.is_down("w"), "held through the delay before repeating");
+        keys.expire(ms(500));
+        assert!(!keys.is_down("W"));
+
+        keys.press("W".to_string(), ms(1000));
+        keys.press("W".to_string(), ms(1500));
+        keys.expire(ms(1599));
+        assert!(keys.is_down("W"), "held a little after each repeat");
+        keys.expire(ms(1600));
+        assert!(!keys.is_down("W"));
+    }
+
+    #[test]
+    fn reported_releases_are_used_instead_of_guessing() {
+        let start = Instant::now();
+        let mut keys = HeldKeys::new(true);
+        keys.press("W".to_string(), start);
+        keys.expire(start + Duration::from_secs(10));
+        assert!(keys.is_down("W"));
+        keys.release("W");
+        assert!(!keys.is_down("W"));
+    }
+
+    #[test]
+    fn held_keys_map_to_chip8_keys() {
+        let mut keys = HeldKeys::new(true);
+        keys.press("W".to_string(), Instant::now());
+        keys.press("V".to_string(), Instant::now());
+        keys.press("Up".to_string(), Instant::now());
+        assert_eq!(keys.chip8_keys(&Keymap::default()), 1 << 0x5 | 1 << 0xF);
+    }
+}
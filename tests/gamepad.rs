@@ -0,0 +1,94 @@
+//! Drives ```Gamepad``` with a uinput virtual device. Needs write access
+//! to ```/dev/uinput```, so these only run when asked for:
+//! ```cargo test --test gamepad -- --ignored```
+#![cfg(target_os = "linux")]
+
+use std::{thread::sleep, time::Duration};
+
+use chip8::{gamepad::Gamepad, keymap::Keymap};
+use evdev::{
+    AbsInfo, AbsoluteAxisCode, AbsoluteAxisEvent, AttributeSet, Device, KeyCode, KeyEvent,
+    UinputAbsSetup, uinput::VirtualDevice,
+};
+
+fn virtual_pad() -> (VirtualDevice, Device) {
+    let builder = VirtualDevice::builder().expect("couldn't open /dev/uinput");
+    let keys: AttributeSet<KeyCode> = [KeyCode::BTN_SOUTH, KeyCode::BTN_EAST]
+        .into_iter()
+        .collect();
+    let hat = AbsInfo::new(0, -1, 1, 0, 0, 0);
+    let mut pad = builder
+        .name("chip8 test pad")
+        .with_keys(&keys)
+        .unwrap()
+        .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_HAT0X, hat))
+        .unwrap()
+        .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_HAT0Y, hat))
+        .unwrap()
+        .build()
+        .unwrap();
+    let path = pad
+        .enumerate_dev_nodes_blocking()
+        .unwrap()
+        .next()
+        .expect("the virtual pad has no device node")
+        .unwrap();
+    let device = Device::open(path).unwrap();
+    (pad, device)
+}
+
+// events take a moment to come through the kernel
+fn poll(gamepad: &mut Gamepad) -> u16 {
+    sleep(Duration::from_millis(50));
+    gamepad.poll().unwrap();
+    gamepad.held_keys()
+}
+
+#[test]
+#[ignore = "needs /dev/uinput"]
+fn buttons_and_hat_press_mapped_keys() {
+    let (mut pad, device) = virtual_pad();
+    let mut gamepad = Gamepad::new(device, &Keymap::gamepad()).unwrap();
+    assert_eq!(poll(&mut gamepad), 0);
+
+    pad.emit(&[*KeyEvent::new(KeyCode::BTN_SOUTH, 1)]).unwrap();
+    assert_eq!(poll(&mut gamepad), 1 << 5);
+
+    pad.emit(&[*AbsoluteAxisEvent::new(AbsoluteAxisCode::ABS_HAT0X, -1)])
+        .unwrap();
+    assert_eq!(poll(&mut gamepad), 1 << 5 | 1 << 4);
+
+    pad.emit(&[
+        *KeyEvent::new(KeyCode::BTN_SOUTH, 0),
+        *AbsoluteAxisEvent::new(AbsoluteAxisCode::ABS_HAT0X, 1),
+    ])
+    .unwrap();
+    assert_eq!(poll(&mut gamepad), 1 << 6);
+
+    // unbound buttons press nothing
+    pad.emit(&[
+        *KeyEvent::new(KeyCode::BTN_EAST, 1),
+        *AbsoluteAxisEvent::new(AbsoluteAxisCode::ABS_HAT0X, 0),
+    ])
+    .unwrap();
+    assert_eq!(poll(&mut gamepad), 0);
+}
+
+#[test]
+#[ignore = "needs /dev/uinput"]
+fn per_rom_bindings_replace_defaults() {
+    let (mut pad, device) = virtual_pad();
+    let mut buttonmap = Keymap::gamepad();
+    let bindings = toml::from_str("5 = []\nA = [\"South\", \"East\"]").unwrap();
+    buttonmap.apply(&bindings).unwrap();
+    let mut gamepad = Gamepad::new(device, &buttonmap).unwrap();
+
+    pad.emit(&[*KeyEvent::new(KeyCode::BTN_EAST, 1)]).unwrap();
+    assert_eq!(poll(&mut gamepad), 1 << 0xA);
+    pad.emit(&[
+        *KeyEvent::new(KeyCode::BTN_EAST, 0),
+        *KeyEvent::new(KeyCode::BTN_SOUTH, 1),
+    ])
+    .unwrap();
+    assert_eq!(poll(&mut gamepad), 1 << 0xA);
+}